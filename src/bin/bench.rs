@@ -11,14 +11,19 @@ struct Options {
     flag_no_default_features: bool,
     flag_target: Option<String>,
     flag_manifest_path: Option<String>,
+    flag_deny_yanked: bool,
     flag_verbose: bool,
     flag_quiet: bool,
     flag_color: Option<String>,
     flag_lib: bool,
     flag_bin: Vec<String>,
+    flag_bins: bool,
     flag_example: Vec<String>,
+    flag_examples: bool,
     flag_test: Vec<String>,
+    flag_tests: bool,
     flag_bench: Vec<String>,
+    flag_benches: bool,
     arg_args: Vec<String>,
 }
 
@@ -32,9 +37,13 @@ Options:
     -h, --help                   Print this message
     --lib                        Benchmark only this package's library
     --bin NAME                   Benchmark only the specified binary
+    --bins                       Benchmark all binaries
     --example NAME               Benchmark only the specified example
+    --examples                   Benchmark all examples
     --test NAME                  Benchmark only the specified test target
+    --tests                      Benchmark all test targets
     --bench NAME                 Benchmark only the specified bench target
+    --benches                    Benchmark all bench targets
     --no-run                     Compile, but don't run benchmarks
     -p SPEC, --package SPEC ...  Package to run benchmarks for
     -j N, --jobs N               The number of jobs to run in parallel
@@ -42,6 +51,7 @@ Options:
     --no-default-features        Do not build the `default` feature
     --target TRIPLE              Build for the target triple
     --manifest-path PATH         Path to the manifest to build benchmarks for
+    --deny-yanked                Fail if Cargo.lock contains yanked packages
     -v, --verbose                Use verbose output
     -q, --quiet                  No output printed to stdout
     --color WHEN                 Coloring: auto, always, never
@@ -50,10 +60,14 @@ All of the trailing arguments are passed to the benchmark binaries generated
 for filtering benchmarks and generally providing options configuring how they
 run.
 
+The NAME given to --bin, --example, --test and --bench may be a glob pattern
+such as `net_*`, selecting every target of that kind whose name matches it.
+
 If the --package argument is given, then SPEC is a package id specification
-which indicates which package should be benchmarked. If it is not given, then
-the current package is benchmarked. For more information on SPEC and its format,
-see the `cargo help pkgid` command.
+which indicates which package should be benchmarked, or a glob pattern
+selecting every package in the dependency graph whose name matches it. If it
+is not given, then the current package is benchmarked. For more information on
+SPEC and its format, see the `cargo help pkgid` command.
 
 The --jobs argument affects the building of the benchmark executable but does
 not affect how many jobs are used when running the benchmarks.
@@ -63,12 +77,21 @@ Compilation can be customized with the `bench` profile in the manifest.
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
     let root = try!(find_root_manifest_for_cwd(options.flag_manifest_path));
+    config.set_deny_yanked(options.flag_deny_yanked);
     try!(config.shell().set_verbosity(options.flag_verbose, options.flag_quiet));
     try!(config.shell().set_color_config(options.flag_color.as_ref().map(|s| &s[..])));
 
+    let filter = ops::CompileFilter::new(options.flag_lib,
+                                         &options.flag_bin, options.flag_bins,
+                                         &options.flag_test, options.flag_tests,
+                                         &options.flag_example, options.flag_examples,
+                                         &options.flag_bench, options.flag_benches);
     let ops = ops::TestOptions {
         no_run: options.flag_no_run,
         no_fail_fast: false,
+        parallel: false,
+        only_doc: false,
+        report: None,
         compile_opts: ops::CompileOptions {
             config: config,
             jobs: options.flag_jobs,
@@ -79,11 +102,7 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
             exec_engine: None,
             release: true,
             mode: ops::CompileMode::Bench,
-            filter: ops::CompileFilter::new(options.flag_lib,
-                                            &options.flag_bin,
-                                            &options.flag_test,
-                                            &options.flag_example,
-                                            &options.flag_bench),
+            filter: filter.with_patterns(),
             target_rustc_args: None,
         },
     };
@@ -13,15 +13,20 @@ struct Options {
     flag_no_default_features: bool,
     flag_target: Option<String>,
     flag_manifest_path: Option<String>,
+    flag_deny_yanked: bool,
     flag_verbose: bool,
     flag_quiet: bool,
     flag_color: Option<String>,
     flag_release: bool,
     flag_lib: bool,
     flag_bin: Vec<String>,
+    flag_bins: bool,
     flag_example: Vec<String>,
+    flag_examples: bool,
     flag_test: Vec<String>,
+    flag_tests: bool,
     flag_bench: Vec<String>,
+    flag_benches: bool,
 }
 
 pub const USAGE: &'static str = "
@@ -36,14 +41,19 @@ Options:
     -j N, --jobs N               The number of jobs to run in parallel
     --lib                        Build only this package's library
     --bin NAME                   Build only the specified binary
+    --bins                       Build all binaries
     --example NAME               Build only the specified example
+    --examples                   Build all examples
     --test NAME                  Build only the specified test target
+    --tests                      Build all test targets
     --bench NAME                 Build only the specified benchmark target
+    --benches                    Build all benchmark targets
     --release                    Build artifacts in release mode, with optimizations
     --features FEATURES          Space-separated list of features to also build
     --no-default-features        Do not build the `default` feature
     --target TRIPLE              Build for the target triple
     --manifest-path PATH         Path to the manifest to compile
+    --deny-yanked                Fail if Cargo.lock contains yanked packages
     -v, --verbose                Use verbose output
     -q, --quiet                  No output printed to stdout
     --color WHEN                 Coloring: auto, always, never
@@ -65,6 +75,7 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
     try!(config.shell().set_color_config(options.flag_color.as_ref().map(|s| &s[..])));
 
     let root = try!(find_root_manifest_for_cwd(options.flag_manifest_path));
+    config.set_deny_yanked(options.flag_deny_yanked);
 
     let opts = CompileOptions {
         config: config,
@@ -77,10 +88,10 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
         mode: ops::CompileMode::Build,
         release: options.flag_release,
         filter: ops::CompileFilter::new(options.flag_lib,
-                                        &options.flag_bin,
-                                        &options.flag_test,
-                                        &options.flag_example,
-                                        &options.flag_bench),
+                                        &options.flag_bin, options.flag_bins,
+                                        &options.flag_test, options.flag_tests,
+                                        &options.flag_example, options.flag_examples,
+                                        &options.flag_bench, options.flag_benches),
         target_rustc_args: None,
     };
 
@@ -0,0 +1,85 @@
+use cargo::ops;
+use cargo::util::{CargoResult, CliResult, CliError, Config, human};
+
+#[derive(RustcDecodable)]
+struct Options {
+    flag_max_age: Option<u64>,
+    flag_max_size: Option<String>,
+    flag_dry_run: bool,
+    flag_verbose: bool,
+    flag_quiet: bool,
+    flag_color: Option<String>,
+}
+
+pub const USAGE: &'static str = "
+Manage the caches of downloaded and checked out sources
+
+Usage:
+    cargo cache gc [options]
+    cargo cache -h | --help
+
+Options:
+    -h, --help                Print this message
+    --max-age DAYS            Remove entries which haven't been used for DAYS
+    --max-size SIZE           Remove the least recently used entries until the
+                              caches fit in SIZE
+    --dry-run                 List what would be removed without removing it
+    -v, --verbose             Use verbose output
+    -q, --quiet               No output printed to stdout
+    --color WHEN              Coloring: auto, always, never
+
+Cargo keeps the crates it downloads from registries, their unpacked sources,
+and the git repositories and checkouts of git dependencies below the home
+directory (which is either `$CARGO_HOME` if set or `$HOME/.cargo` by default).
+The target directory `cargo install` builds in is kept as well, and is cleaned
+out as a single entry.
+Each time a build uses one of these entries the time at which it was used is
+recorded, and `cargo cache gc` removes the entries which are no longer used.
+
+With `--max-age` entries which haven't been used for the given number of days
+are removed. With `--max-size` the least recently used entries are removed
+until all of the caches together are no bigger than SIZE, which is a number of
+bytes optionally followed by one of the suffixes `K`, `M` or `G`. When both are
+given, entries which satisfy either condition are removed.
+
+The collection waits for running builds to finish before removing anything,
+and builds wait for the collection to finish before resolving their
+dependencies, so it's safe to collect garbage while builds are running.
+Entries used within the last hour are never removed either, which protects
+what commands such as `cargo fetch` have just downloaded. Anything which is
+removed but needed again later is simply downloaded or checked out again.
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
+    try!(config.shell().set_verbosity(options.flag_verbose, options.flag_quiet));
+    try!(config.shell().set_color_config(options.flag_color.as_ref().map(|s| &s[..])));
+
+    let max_size = match options.flag_max_size {
+        Some(ref size) => Some(try!(parse_size(size).map_err(|e| {
+            CliError::from_boxed(e, 1)
+        }))),
+        None => None,
+    };
+    let opts = ops::CacheGcOptions {
+        config: config,
+        max_age: options.flag_max_age,
+        max_size: max_size,
+        dry_run: options.flag_dry_run,
+    };
+    try!(ops::cache_gc(&opts));
+    Ok(None)
+}
+
+fn parse_size(size: &str) -> CargoResult<u64> {
+    let (digits, unit) = match size.chars().last() {
+        Some('K') | Some('k') => (&size[..size.len() - 1], 1 << 10),
+        Some('M') | Some('m') => (&size[..size.len() - 1], 1 << 20),
+        Some('G') | Some('g') => (&size[..size.len() - 1], 1 << 30),
+        _ => (size, 1),
+    };
+    match digits.parse::<u64>() {
+        Ok(n) => Ok(n * unit),
+        Err(..) => Err(human(format!("invalid size `{}`, expected a number of \
+                                      bytes such as `500M` or `2G`", size))),
+    }
+}
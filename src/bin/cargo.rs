@@ -45,6 +45,7 @@ Some common cargo commands are:
     build       Compile the current project
     clean       Remove the target directory
     doc         Build this project's and its dependencies' documentation
+    init        Create a new cargo project in an existing directory
     new         Create a new cargo project
     run         Build and execute src/main.rs
     test        Run the tests
@@ -63,16 +64,19 @@ fn main() {
 macro_rules! each_subcommand{ ($mac:ident) => ({
     $mac!(bench);
     $mac!(build);
+    $mac!(cache);
     $mac!(clean);
     $mac!(doc);
     $mac!(fetch);
     $mac!(generate_lockfile);
     $mac!(git_checkout);
     $mac!(help);
+    $mac!(init);
     $mac!(install);
     $mac!(locate_project);
     $mac!(login);
     $mac!(new);
+    $mac!(outdated);
     $mac!(owner);
     $mac!(package);
     $mac!(pkgid);
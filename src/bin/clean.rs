@@ -9,6 +9,9 @@ struct Options {
     flag_package: Vec<String>,
     flag_target: Option<String>,
     flag_manifest_path: Option<String>,
+    flag_release: bool,
+    flag_doc: bool,
+    flag_dry_run: bool,
     flag_verbose: bool,
     flag_quiet: bool,
     flag_color: Option<String>,
@@ -25,6 +28,9 @@ Options:
     -p SPEC, --package SPEC ...  Package to clean artifacts for
     --manifest-path PATH         Path to the manifest to the package to clean
     --target TRIPLE              Target triple to clean output for (default all)
+    --release                    Only clean the artifacts of the release profile
+    --doc                        Only clean the documentation
+    --dry-run                    List what would be removed without removing it
     -v, --verbose                Use verbose output
     -q, --quiet                  No output printed to stdout
     --color WHEN                 Coloring: auto, always, never
@@ -33,6 +39,14 @@ If the --package argument is given, then SPEC is a package id specification
 which indicates which package's artifacts should be cleaned out. If it is not
 given, then all packages' artifacts are removed. For more information on SPEC
 and its format, see the `cargo help pkgid` command.
+
+The artifacts of every profile and the generated documentation are removed
+unless `--release` or `--doc` (which may be combined) select only some of them.
+With `--target` only the output for that target triple is cleaned, otherwise
+the output for the host and the whole target directory are.
+
+Passing `--dry-run` lists the files and directories which would be removed,
+along with how much space they take up, without removing anything.
 ";
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
@@ -45,6 +59,9 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
         config: config,
         spec: &options.flag_package,
         target: options.flag_target.as_ref().map(|s| &s[..]),
+        release: options.flag_release,
+        doc: options.flag_doc,
+        dry_run: options.flag_dry_run,
     };
     ops::clean(&root, &opts).map(|_| None).map_err(|err| {
       CliError::from_boxed(err, 101)
@@ -5,6 +5,7 @@ use cargo::util::important_paths::find_root_manifest_for_cwd;
 #[derive(RustcDecodable)]
 struct Options {
     flag_manifest_path: Option<String>,
+    flag_deny_yanked: bool,
     flag_verbose: bool,
     flag_quiet: bool,
     flag_color: Option<String>,
@@ -19,6 +20,7 @@ Usage:
 Options:
     -h, --help               Print this message
     --manifest-path PATH     Path to the manifest to fetch dependencies for
+    --deny-yanked            Fail if Cargo.lock contains yanked packages
     -v, --verbose            Use verbose output
     -q, --quiet              No output printed to stdout
     --color WHEN             Coloring: auto, always, never
@@ -37,6 +39,7 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
     try!(config.shell().set_verbosity(options.flag_verbose, options.flag_quiet));
     try!(config.shell().set_color_config(options.flag_color.as_ref().map(|s| &s[..])));
     let root = try!(find_root_manifest_for_cwd(options.flag_manifest_path));
+    config.set_deny_yanked(options.flag_deny_yanked);
     try!(ops::fetch(&root, config).map_err(|e| {
         CliError::from_boxed(e, 101)
     }));
@@ -0,0 +1,68 @@
+use std::env;
+
+use cargo::ops;
+use cargo::util::{CliResult, CliError, Config};
+
+#[derive(RustcDecodable)]
+struct Options {
+    flag_verbose: bool,
+    flag_quiet: bool,
+    flag_color: Option<String>,
+    flag_bin: bool,
+    arg_path: Option<String>,
+    flag_name: Option<String>,
+    flag_vcs: Option<ops::VersionControl>,
+    flag_template: Option<String>,
+}
+
+pub const USAGE: &'static str = "
+Create a new cargo package in an existing directory
+
+Usage:
+    cargo init [options] [<path>]
+    cargo init -h | --help
+
+Options:
+    -h, --help          Print this message
+    --vcs VCS           Initialize a new repository for the given version
+                        control system (git or hg) or do not initialize any version
+                        control at all (none) overriding a global configuration.
+    --bin               Use a binary instead of a library template
+    --name NAME         Set the resulting package name
+    --template DIR      Directory or git repository URL of a template to use
+    -v, --verbose       Use verbose output
+    -q, --quiet         No output printed to stdout
+    --color WHEN        Coloring: auto, always, never
+
+The package is created in <path>, or the current directory if it is omitted.
+Existing source files are used as the package's targets: `src/main.rs` or
+`main.rs` as a binary, `src/lib.rs` or `lib.rs` as a library, and `<name>.rs`
+as a binary if it defines a `main` function or as a library otherwise. If none
+of these exist, a new binary or library template is created as by `cargo new`.
+
+Entries are added to an existing `.gitignore` or `.hgignore` file rather than
+replacing it, and no existing file is ever overwritten. As Mercurial only reads
+the `.hgignore` at the root of the repository, the entries added to it are
+anchored to the directory of the project.
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
+    debug!("executing; cmd=cargo-init; args={:?}", env::args().collect::<Vec<_>>());
+    try!(config.shell().set_verbosity(options.flag_verbose, options.flag_quiet));
+    try!(config.shell().set_color_config(options.flag_color.as_ref().map(|s| &s[..])));
+
+    let Options { flag_bin, arg_path, flag_name, flag_vcs, flag_template, .. } = options;
+
+    let path = arg_path.unwrap_or(".".to_string());
+    let opts = ops::NewOptions {
+        version_control: flag_vcs,
+        bin: flag_bin,
+        path: &path,
+        name: flag_name.as_ref().map(|s| s.as_ref()),
+        template: flag_template.as_ref().map(|s| s.as_ref()),
+    };
+
+    ops::init(opts, config).map(|_| None).map_err(|err| {
+        CliError::from_boxed(err, 101)
+    })
+}
@@ -17,8 +17,11 @@ struct Options {
     flag_color: Option<String>,
     flag_root: Option<String>,
     flag_list: bool,
+    flag_outdated: bool,
+    flag_force: bool,
+    flag_locked: bool,
 
-    arg_crate: Option<String>,
+    arg_crate: Vec<String>,
     flag_vers: Option<String>,
 
     flag_git: Option<String>,
@@ -33,8 +36,8 @@ pub const USAGE: &'static str = "
 Install a Rust binary
 
 Usage:
-    cargo install [options] [<crate>]
-    cargo install [options] --list
+    cargo install [options] [<crate>...]
+    cargo install [options] --list [--outdated]
 
 Specifying what crate to install:
     --vers VERS               Specify a version to install from crates.io
@@ -53,6 +56,8 @@ Build and install options:
     --bin NAME                Only install the binary NAME
     --example EXAMPLE         Install the example EXAMPLE instead of binaries
     --root DIR                Directory to install packages into
+    -f, --force               Force overwriting existing crates or binaries
+    --locked                  Require the crate's Cargo.lock to be up to date
     -v, --verbose             Use verbose output
     -q, --quiet               Less output printed to stdout
     --color WHEN              Coloring: auto, always, never
@@ -70,6 +75,12 @@ If the source contains more than one package (such as crates.io or a git
 repository with multiple crates) the `<crate>` argument is required to indicate
 which crate should be installed.
 
+Several crates can be installed at once by naming each of them. They are all
+built before any of them is installed, and if one of them fails nothing is
+installed. The remaining crates are still built so that all failures are
+reported at the end. Crates from a git repository are all installed from the
+same revision.
+
 Crates from crates.io can optionally specify the version they wish to install
 via the `--vers` flags, and similarly packages from git repositories can
 optionally specify the branch, tag, or revision that should be installed. If a
@@ -77,12 +88,32 @@ crate has multiple binaries, the `--bin` argument can selectively install only
 one of them, and if you'd rather install examples the `--example` argument can
 be used as well.
 
-The `--list` option will list all installed packages (and their versions).
+If a binary to be installed already exists it is only replaced if it was
+installed as part of an older version (or for git repositories, another
+revision) of the same package, which upgrades that package in place. The
+`--force` flag allows overwriting any existing binary and reinstalling the
+same version of a package.
+
+Passing `--locked` builds the crate with exactly the dependencies recorded in
+its `Cargo.lock` (which packages with binaries include when published). The
+installation fails if the lock file is missing or needs to be updated.
+
+Crates are built in a target directory shared by all installations, which is
+`$CARGO_HOME/target-install` unless the `install.target-dir` configuration key
+is set. It is kept after installing so that dependencies common to several
+crates only need to be compiled once. `cargo cache gc` cleans it out once it
+hasn't been used for long enough, and it can also be removed by hand whenever
+no installation is running.
+
+The `--list` option will list all installed packages (and their versions). With
+`--outdated` only the packages installed from a registry for which a newer
+version has been published are listed.
 ";
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
     try!(config.shell().set_verbosity(options.flag_verbose, options.flag_quiet));
     try!(config.shell().set_color_config(options.flag_color.as_ref().map(|s| &s[..])));
+    config.set_locked(options.flag_locked);
 
     let compile_opts = ops::CompileOptions {
         config: config,
@@ -94,8 +125,10 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
         exec_engine: None,
         mode: ops::CompileMode::Build,
         release: !options.flag_debug,
-        filter: ops::CompileFilter::new(false, &options.flag_bin, &[],
-                                        &options.flag_example, &[]),
+        filter: ops::CompileFilter::new(false, &options.flag_bin, false,
+                                        &[], false,
+                                        &options.flag_example, false,
+                                        &[], false),
         target_rustc_args: None,
     };
 
@@ -117,14 +150,15 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
         try!(SourceId::for_central(config))
     };
 
-    let krate = options.arg_crate.as_ref().map(|s| &s[..]);
+    let krates = options.arg_crate.iter().map(|s| &s[..]).collect();
     let vers = options.flag_vers.as_ref().map(|s| &s[..]);
     let root = options.flag_root.as_ref().map(|s| &s[..]);
 
     if options.flag_list {
-        try!(ops::install_list(root, config));
+        try!(ops::install_list(root, options.flag_outdated, config));
     } else {
-        try!(ops::install(root, krate, &source, vers, &compile_opts));
+        try!(ops::install(root, krates, &source, vers, options.flag_force,
+                          &compile_opts));
     }
     Ok(None)
 }
@@ -12,6 +12,7 @@ struct Options {
     arg_path: String,
     flag_name: Option<String>,
     flag_vcs: Option<ops::VersionControl>,
+    flag_template: Option<String>,
 }
 
 pub const USAGE: &'static str = "
@@ -28,9 +29,21 @@ Options:
                         control at all (none) overriding a global configuration.
     --bin               Use a binary instead of a library template
     --name NAME         Set the resulting package name
+    --template DIR      Directory or git repository URL of a template to use
     -v, --verbose       Use verbose output
     -q, --quiet         No output printed to stdout
     --color WHEN        Coloring: auto, always, never
+
+A template is a directory, or a git repository given by its URL, whose files
+are copied into the new package. The placeholders `{{name}}`, `{{authors}}` and
+`{{year}}` in them are replaced by the package's name, its author and the
+current year. They're inserted as they are, except in `Cargo.toml`, where they
+are escaped to be used within strings. A template may leave out `Cargo.toml` or
+the package's sources, in which case the usual ones are created. The
+`cargo-new.template` configuration key sets a template to use by default.
+
+The `master` branch of a git repository is used, unless another branch is
+given after a `#` at the end of its URL, as in `<url>#<branch>`.
 ";
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
@@ -38,13 +51,14 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
     try!(config.shell().set_verbosity(options.flag_verbose, options.flag_quiet));
     try!(config.shell().set_color_config(options.flag_color.as_ref().map(|s| &s[..])));
 
-    let Options { flag_bin, arg_path, flag_name, flag_vcs, .. } = options;
+    let Options { flag_bin, arg_path, flag_name, flag_vcs, flag_template, .. } = options;
 
     let opts = ops::NewOptions {
         version_control: flag_vcs,
         bin: flag_bin,
         path: &arg_path,
         name: flag_name.as_ref().map(|s| s.as_ref()),
+        template: flag_template.as_ref().map(|s| s.as_ref()),
     };
 
     ops::new(opts, config).map(|_| None).map_err(|err| {
@@ -0,0 +1,60 @@
+use std::env;
+
+use cargo::ops::{self, OutdatedPackage};
+use cargo::util::{CliResult, CliError, Config};
+use cargo::util::important_paths::find_root_manifest_for_cwd;
+
+#[derive(RustcDecodable)]
+struct Options {
+    flag_manifest_path: Option<String>,
+    flag_json: bool,
+    flag_verbose: bool,
+    flag_quiet: bool,
+    flag_color: Option<String>,
+}
+
+pub const USAGE: &'static str = "
+Display dependencies which have newer versions available.
+
+Usage:
+    cargo outdated [options]
+
+Options:
+    -h, --help               Print this message
+    --manifest-path PATH     Path to the manifest to inspect
+    --json                   Print the report as JSON
+    -v, --verbose            Use verbose output
+    -q, --quiet              No output printed to stdout
+    --color WHEN             Coloring: auto, always, never
+
+Every package from a registry in the dependency graph, both direct and
+transitive dependencies, is compared against the versions available in its
+registry. For each package which has a newer version available the currently
+locked version, the newest semver compatible version (what `cargo update`
+would pick) and the newest version overall are printed. Yanked versions are
+never reported.
+";
+
+pub fn execute(options: Options, config: &Config)
+               -> CliResult<Option<Vec<OutdatedPackage>>> {
+    debug!("executing; cmd=cargo-outdated; args={:?}",
+           env::args().collect::<Vec<_>>());
+    // Status messages would corrupt the JSON printed on stdout
+    let quiet = options.flag_quiet || options.flag_json;
+    try!(config.shell().set_verbosity(options.flag_verbose, quiet));
+    try!(config.shell().set_color_config(options.flag_color.as_ref().map(|s| &s[..])));
+    let root = try!(find_root_manifest_for_cwd(options.flag_manifest_path));
+
+    let report = try!(ops::outdated(&root, config).map_err(|err| {
+        CliError::from_boxed(err, 101)
+    }));
+
+    if options.flag_json {
+        return Ok(Some(report))
+    }
+
+    try!(ops::print_outdated(&report, config).map_err(|err| {
+        CliError::from_boxed(err, 101)
+    }));
+    Ok(None)
+}
@@ -36,6 +36,10 @@ default). Note that owners of a package can upload new versions, yank old
 versions. Explicitly named owners can also modify the set of owners, so take
 caution!
 
+Owners can be users, named by their login, or GitHub teams, named as
+`github:org:team`. Adding a team gives every member of it permission to publish
+new versions and yank old ones, but not to change the set of owners.
+
 See http://doc.crates.io/crates-io.html#cargo-owner for detailed documentation
 and troubleshooting.
 ";
@@ -10,7 +10,9 @@ struct Options {
     flag_manifest_path: Option<String>,
     flag_no_verify: bool,
     flag_no_metadata: bool,
+    flag_allow_dirty: bool,
     flag_list: bool,
+    flag_print_checksum: bool,
 }
 
 pub const USAGE: &'static str = "
@@ -24,21 +26,44 @@ Options:
     -l, --list              Print files included in a package without making one
     --no-verify             Don't verify the contents by building them
     --no-metadata           Ignore warnings about a lack of human-usable metadata
+    --allow-dirty           Allow packaging with uncommitted changes in git
+    --print-checksum        Print the sha256 checksum of the packaged tarball
     --manifest-path PATH    Path to the manifest to compile
     -v, --verbose           Use verbose output
     -q, --quiet             No output printed to stdout
     --color WHEN            Coloring: auto, always, never
 
+Packaging the same sources always produces an identical tarball. Every entry
+in the tarball is given the same modification time, which can be chosen by
+setting the SOURCE_DATE_EPOCH environment variable.
+
+If the package is at the root of a git repository, packaging fails when any
+of the files to be packaged have uncommitted changes or are untracked, unless
+--allow-dirty is passed. Otherwise the hash of the current commit is recorded
+in the tarball.
+
+The checksum printed by --print-checksum is the one the registry records for
+the package when it is published.
 ";
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
     try!(config.shell().set_verbosity(options.flag_verbose, options.flag_quiet));
     try!(config.shell().set_color_config(options.flag_color.as_ref().map(|s| &s[..])));
     let root = try!(find_root_manifest_for_cwd(options.flag_manifest_path));
-    ops::package(&root, config,
-                 !options.flag_no_verify,
-                 options.flag_list,
-                 !options.flag_no_metadata).map(|_| None).map_err(|err| {
+    let dst = try!(ops::package(&root, &ops::PackageOpts {
+        config: config,
+        verify: !options.flag_no_verify,
+        list: options.flag_list,
+        check_metadata: !options.flag_no_metadata,
+        allow_dirty: options.flag_allow_dirty,
+    }).map_err(|err| {
         CliError::from_boxed(err, 101)
-    })
+    }));
+    if let (true, Some(dst)) = (options.flag_print_checksum, dst) {
+        let checksum = try!(ops::package_checksum(&dst).map_err(|err| {
+            CliError::from_boxed(err, 101)
+        }));
+        println!("{}", checksum);
+    }
+    Ok(None)
 }
@@ -11,6 +11,8 @@ struct Options {
     flag_quiet: bool,
     flag_color: Option<String>,
     flag_no_verify: bool,
+    flag_allow_dirty: bool,
+    flag_dry_run: bool,
 }
 
 pub const USAGE: &'static str = "
@@ -24,26 +26,29 @@ Options:
     --host HOST              Host to upload the package to
     --token TOKEN            Token to use when uploading
     --no-verify              Don't verify package tarball before publish
+    --allow-dirty            Allow publishing with uncommitted changes in git
+    --dry-run                Perform all checks without uploading
     --manifest-path PATH     Path to the manifest to compile
     -v, --verbose            Use verbose output
     -q, --quiet              No output printed to stdout
     --color WHEN             Coloring: auto, always, never
 
+With --dry-run the package is packaged, verified and checked against the limits
+of the registry exactly as it would be when publishing, and a summary of what
+would be uploaded is printed instead of uploading it.
 ";
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
     try!(config.shell().set_verbosity(options.flag_verbose, options.flag_quiet));
     try!(config.shell().set_color_config(options.flag_color.as_ref().map(|s| &s[..])));
-    let Options {
-        flag_token: token,
-        flag_host: host,
-        flag_manifest_path,
-        flag_no_verify: no_verify,
-        ..
-    } = options;
-
-    let root = try!(find_root_manifest_for_cwd(flag_manifest_path.clone()));
-    ops::publish(&root, config, token, host, !no_verify).map(|_| None).map_err(|err| {
+    let root = try!(find_root_manifest_for_cwd(options.flag_manifest_path));
+    ops::publish(&root, config, &ops::PublishOpts {
+        token: options.flag_token,
+        index: options.flag_host,
+        verify: !options.flag_no_verify,
+        allow_dirty: options.flag_allow_dirty,
+        dry_run: options.flag_dry_run,
+    }).map(|_| None).map_err(|err| {
         CliError::from_boxed(err, 101)
     })
 }
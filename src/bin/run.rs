@@ -11,6 +11,7 @@ struct Options {
     flag_no_default_features: bool,
     flag_target: Option<String>,
     flag_manifest_path: Option<String>,
+    flag_deny_yanked: bool,
     flag_verbose: bool,
     flag_quiet: bool,
     flag_color: Option<String>,
@@ -34,6 +35,7 @@ Options:
     --no-default-features   Do not build the `default` feature
     --target TRIPLE         Build for the target triple
     --manifest-path PATH    Path to the manifest to execute
+    --deny-yanked           Fail if Cargo.lock contains yanked packages
     -v, --verbose           Use verbose output
     -q, --quiet             No output printed to stdout
     --color WHEN            Coloring: auto, always, never
@@ -53,6 +55,7 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
     try!(config.shell().set_color_config(options.flag_color.as_ref().map(|s| &s[..])));
 
     let root = try!(find_root_manifest_for_cwd(options.flag_manifest_path));
+    config.set_deny_yanked(options.flag_deny_yanked);
 
     let (mut examples, mut bins) = (Vec::new(), Vec::new());
     if let Some(s) = options.flag_bin {
@@ -76,8 +79,11 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
             ops::CompileFilter::Everything
         } else {
             ops::CompileFilter::Only {
-                lib: false, tests: &[], benches: &[],
-                bins: &bins, examples: &examples,
+                lib: false,
+                tests: ops::FilterRule::Just(&[]),
+                benches: ops::FilterRule::Just(&[]),
+                bins: ops::FilterRule::Just(&bins),
+                examples: ops::FilterRule::Just(&examples),
             }
         },
         target_rustc_args: None,
@@ -80,10 +80,10 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
         mode: ops::CompileMode::Build,
         release: options.flag_release,
         filter: ops::CompileFilter::new(options.flag_lib,
-                                        &options.flag_bin,
-                                        &options.flag_test,
-                                        &options.flag_example,
-                                        &options.flag_bench),
+                                        &options.flag_bin, false,
+                                        &options.flag_test, false,
+                                        &options.flag_example, false,
+                                        &options.flag_bench, false),
         target_rustc_args: options.arg_opts.as_ref().map(|a| &a[..]),
     };
 
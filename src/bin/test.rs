@@ -8,20 +8,29 @@ struct Options {
     flag_features: Vec<String>,
     flag_jobs: Option<u32>,
     flag_manifest_path: Option<String>,
+    flag_deny_yanked: bool,
     flag_no_default_features: bool,
     flag_no_run: bool,
     flag_package: Vec<String>,
     flag_target: Option<String>,
     flag_lib: bool,
+    flag_doc: bool,
     flag_bin: Vec<String>,
+    flag_bins: bool,
     flag_example: Vec<String>,
+    flag_examples: bool,
     flag_test: Vec<String>,
+    flag_tests: bool,
     flag_bench: Vec<String>,
+    flag_benches: bool,
     flag_verbose: bool,
     flag_quiet: bool,
     flag_color: Option<String>,
     flag_release: bool,
     flag_no_fail_fast: bool,
+    flag_parallel: bool,
+    flag_report: Option<String>,
+    flag_report_format: Option<ops::ReportFormat>,
 }
 
 pub const USAGE: &'static str = "
@@ -33,10 +42,15 @@ Usage:
 Options:
     -h, --help                   Print this message
     --lib                        Test only this package's library
+    --doc                        Test only this library's documentation
     --bin NAME                   Test only the specified binary
+    --bins                       Test all binaries
     --example NAME               Test only the specified example
+    --examples                   Test all examples
     --test NAME                  Test only the specified integration test target
+    --tests                      Test all integration test targets
     --bench NAME                 Test only the specified benchmark target
+    --benches                    Test all benchmark targets
     --no-run                     Compile, but don't run tests
     -p SPEC, --package SPEC ...  Package to run tests for
     -j N, --jobs N               The number of jobs to run in parallel
@@ -45,10 +59,14 @@ Options:
     --no-default-features        Do not build the `default` feature
     --target TRIPLE              Build for the target triple
     --manifest-path PATH         Path to the manifest to build tests for
+    --deny-yanked                Fail if Cargo.lock contains yanked packages
     -v, --verbose                Use verbose output
     -q, --quiet                  No output printed to stdout
     --color WHEN                 Coloring: auto, always, never
     --no-fail-fast               Run all tests regardless of failure
+    --parallel                   Run up to --jobs test binaries at the same time
+    --report FILE                Write a report of the test results to FILE
+    --report-format FORMAT       Format of the report: json (default), junit
 
 All of the trailing arguments are passed to the test binaries generated for
 filtering tests and generally providing options configuring how they run. For
@@ -57,24 +75,65 @@ example, this will run all tests with the name `foo` in their name:
     cargo test foo
 
 If the --package argument is given, then SPEC is a package id specification
-which indicates which package should be tested. If it is not given, then the
-current package is tested. For more information on SPEC and its format, see the
-`cargo help pkgid` command.
+which indicates which package should be tested, or a glob pattern selecting
+every package in the dependency graph whose name matches it. If it is not
+given, then the current package is tested. For more information on SPEC and
+its format, see the `cargo help pkgid` command.
 
-The --jobs argument affects the building of the test executable but does
-not affect how many jobs are used when running the tests.
+The NAME given to --bin, --example, --test and --bench may be a glob pattern
+such as `net_*`, selecting every target of that kind whose name matches it.
+
+With --doc, only the examples in the documentation of the targets are
+tested, and no unit or integration test binaries are built or run. It can't
+be combined with the other options selecting which targets to test. The
+documentation of binaries, examples and integration tests is only tested if
+their section of the manifest sets `doctest = true`.
+
+With --report, a single report covering every test binary and the doc tests
+is written once all tests have run. It lists the package and kind of target of
+each binary, how long it ran, its exit status, and the outcome of each test
+which the test harness printed. The report is JSON unless --report-format
+asks for JUnit XML.
+
+The --jobs argument affects the building of the test executable. It only
+affects how many jobs are used when running the tests if --parallel is given,
+in which case up to that many test binaries run at the same time. The output
+of each binary is printed all at once when it finishes. Doc tests always run
+one after another.
 
 Compilation can be configured via the `test` profile in the manifest.
 ";
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
     let root = try!(find_root_manifest_for_cwd(options.flag_manifest_path));
+    config.set_deny_yanked(options.flag_deny_yanked);
     try!(config.shell().set_verbosity(options.flag_verbose, options.flag_quiet));
     try!(config.shell().set_color_config(options.flag_color.as_ref().map(|s| &s[..])));
 
+    let filter = ops::CompileFilter::new(options.flag_lib,
+                                         &options.flag_bin, options.flag_bins,
+                                         &options.flag_test, options.flag_tests,
+                                         &options.flag_example, options.flag_examples,
+                                         &options.flag_bench, options.flag_benches).with_patterns();
+    let mode = if options.flag_doc {
+        if let ops::CompileFilter::Only { .. } = filter {
+            return Err(CliError::new("Can't mix --doc with other target \
+                                      selecting options", 101))
+        }
+        ops::CompileMode::Doctest
+    } else {
+        ops::CompileMode::Test
+    };
+
+    let report = options.flag_report.as_ref().map(|path| config.cwd().join(path));
+    let report_format = options.flag_report_format.unwrap_or(ops::ReportFormat::Json);
+
     let ops = ops::TestOptions {
         no_run: options.flag_no_run,
         no_fail_fast: options.flag_no_fail_fast,
+        parallel: options.flag_parallel,
+        only_doc: options.flag_doc,
+        report: report.as_ref().map(|path| (&**path, report_format)),
         compile_opts: ops::CompileOptions {
             config: config,
             jobs: options.flag_jobs,
@@ -84,12 +143,8 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
             spec: &options.flag_package,
             exec_engine: None,
             release: options.flag_release,
-            mode: ops::CompileMode::Test,
-            filter: ops::CompileFilter::new(options.flag_lib,
-                                            &options.flag_bin,
-                                            &options.flag_test,
-                                            &options.flag_example,
-                                            &options.flag_bench),
+            mode: mode,
+            filter: filter,
             target_rustc_args: None,
         },
     };
@@ -1,6 +1,6 @@
 use std::env;
 
-use cargo::ops;
+use cargo::ops::{self, LockfileChange};
 use cargo::util::{CliResult, CliError, Config};
 use cargo::util::important_paths::find_root_manifest_for_cwd;
 
@@ -9,6 +9,8 @@ struct Options {
     flag_package: Vec<String>,
     flag_aggressive: bool,
     flag_precise: Option<String>,
+    flag_dry_run: bool,
+    flag_json: bool,
     flag_manifest_path: Option<String>,
     flag_verbose: bool,
     flag_quiet: bool,
@@ -26,6 +28,8 @@ Options:
     -p SPEC, --package SPEC ...  Package to update
     --aggressive                 Force updating all dependencies of <name> as well
     --precise PRECISE            Update a single dependency to exactly PRECISE
+    --dry-run                    Print the changes without writing Cargo.lock
+    --json                       Print the changes as JSON
     --manifest-path PATH         Path to the manifest to compile
     -v, --verbose                Use verbose output
     -q, --quiet                  No output printed to stdout
@@ -49,12 +53,19 @@ be updated to.
 If SPEC is not given, then all dependencies will be re-resolved and
 updated.
 
+With --dry-run the new lockfile is computed and every added, removed,
+upgraded, downgraded or moved package is printed, but Cargo.lock is left
+untouched. Passing --json prints the same list as a JSON array instead.
+
 For more information about package id specifications, see `cargo help pkgid`.
 ";
 
-pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
+pub fn execute(options: Options, config: &Config)
+               -> CliResult<Option<Vec<LockfileChange>>> {
     debug!("executing; cmd=cargo-update; args={:?}", env::args().collect::<Vec<_>>());
-    try!(config.shell().set_verbosity(options.flag_verbose, options.flag_quiet));
+    // Status messages would corrupt the JSON printed on stdout
+    let quiet = options.flag_quiet || options.flag_json;
+    try!(config.shell().set_verbosity(options.flag_verbose, quiet));
     try!(config.shell().set_color_config(options.flag_color.as_ref().map(|s| &s[..])));
     let root = try!(find_root_manifest_for_cwd(options.flag_manifest_path));
 
@@ -62,10 +73,17 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
         aggressive: options.flag_aggressive,
         precise: options.flag_precise.as_ref().map(|s| &s[..]),
         to_update: &options.flag_package,
+        dry_run: options.flag_dry_run,
         config: config,
     };
 
-    ops::update_lockfile(&root, &update_opts)
-        .map(|_| None).map_err(|err| CliError::from_boxed(err, 101))
+    let changes = try!(ops::update_lockfile(&root, &update_opts).map_err(|err| {
+        CliError::from_boxed(err, 101)
+    }));
+    if options.flag_json {
+        Ok(Some(changes))
+    } else {
+        Ok(None)
+    }
 }
 
@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::default::Default;
 use std::fmt;
 use std::path::{PathBuf, Path};
@@ -35,6 +36,7 @@ pub struct Manifest {
 pub struct ManifestMetadata {
     pub authors: Vec<String>,
     pub keywords: Vec<String>,
+    pub categories: Vec<String>,
     pub license: Option<String>,
     pub license_file: Option<String>,
     pub description: Option<String>,    // not markdown
@@ -42,6 +44,7 @@ pub struct ManifestMetadata {
     pub homepage: Option<String>,       // url
     pub repository: Option<String>,     // url
     pub documentation: Option<String>,  // url
+    pub badges: BTreeMap<String, BTreeMap<String, String>>,
 }
 
 #[derive(PartialEq,Clone,RustcEncodable)]
@@ -326,7 +329,9 @@ impl Target {
             TargetKind::Lib(ref kinds) => {
                 kinds.contains(&LibKind::Rlib) || kinds.contains(&LibKind::Lib)
             }
-            _ => false,
+            TargetKind::Bin | TargetKind::Example |
+            TargetKind::Test | TargetKind::Bench => true,
+            TargetKind::CustomBuild => false,
         }
     }
 
@@ -107,6 +107,10 @@ impl<'cfg> PackageRegistry<'cfg> {
         Ok(ret)
     }
 
+    pub fn config(&self) -> &'cfg Config {
+        self.config
+    }
+
     pub fn move_sources(self) -> SourceMap<'cfg> {
         self.sources
     }
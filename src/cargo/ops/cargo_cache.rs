@@ -0,0 +1,223 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use term::color::BLACK;
+use time;
+
+use ops::cargo_install::install_target_dir;
+use util::{CargoResult, ChainError, Config, human, paths, last_use, flock};
+
+pub struct CacheGcOptions<'a> {
+    pub config: &'a Config,
+    /// Remove the entries which haven't been used for this many days.
+    pub max_age: Option<u64>,
+    /// Remove the least recently used entries until the caches take up at
+    /// most this many bytes.
+    pub max_size: Option<u64>,
+    /// List what would be removed instead of removing it.
+    pub dry_run: bool,
+}
+
+/// Entries used this recently are never removed. Builds keep the caches locked
+/// while they use them, but other commands such as `cargo fetch` don't, and
+/// this keeps what they've just downloaded intact.
+const GRACE_PERIOD: u64 = 60 * 60;
+
+const DAY: u64 = 24 * 60 * 60;
+
+struct Entry {
+    path: PathBuf,
+    last_use: u64,
+    size: u64,
+    /// Whether only the contents of the directory are removed, keeping the
+    /// lock files in it.
+    contents_only: bool,
+}
+
+/// Removes the entries of the caches in `CARGO_HOME` (downloaded crates,
+/// unpacked sources, git databases and git checkouts) which haven't been used
+/// recently enough, or which don't fit in the size budget. The target
+/// directory of `cargo install` counts as a single entry.
+///
+/// The least recently used entries are removed first.
+pub fn cache_gc(opts: &CacheGcOptions) -> CargoResult<()> {
+    let config = opts.config;
+    if opts.max_age.is_none() && opts.max_size.is_none() {
+        return Err(human("either a maximum age or a maximum size of the \
+                          caches must be given"))
+    }
+
+    // Installations hold this lock while building, before taking any of the
+    // locks below. A directory which doesn't exist yet has nothing to remove,
+    // and isn't created just to lock it.
+    let install_dir = try!(install_target_dir(config));
+    let install_lock = if fs::metadata(&install_dir).is_ok() {
+        Some(try!(flock::unused(&install_dir, "the install target directory",
+                                config)))
+    } else {
+        None
+    };
+
+    // Builds hold these locks from resolving their dependencies until they've
+    // been compiled, so nothing which a running build uses is removed. They're
+    // taken first as builds take them before the ones below.
+    let _in_use = (
+        try!(flock::unused(&config.registry_cache_path(), "the package cache",
+                           config)),
+        try!(flock::unused(&config.registry_source_path(),
+                           "the package sources", config)),
+        try!(flock::unused(&config.git_db_path(), "the git database", config)),
+        try!(flock::unused(&config.git_checkout_path(), "the git checkouts",
+                           config)),
+    );
+
+    // Builds take these locks while downloading or checking out sources, so
+    // nothing can be added to the caches while they're being collected.
+    let _locks = (
+        try!(flock::exclusive(&config.registry_cache_path(), "the package cache",
+                              config)),
+        try!(flock::exclusive(&config.git_db_path(), "the git database", config)),
+        try!(flock::exclusive(&config.git_checkout_path(), "the git checkouts",
+                              config)),
+    );
+
+    let now = time::get_time().sec as u64;
+    let mut entries = Vec::new();
+    try!(collect(&config.registry_cache_path(), 2, now, opts, &mut entries));
+    try!(collect(&config.registry_source_path(), 2, now, opts, &mut entries));
+    try!(collect(&config.git_db_path(), 1, now, opts, &mut entries));
+    try!(collect(&config.git_checkout_path(), 2, now, opts, &mut entries));
+    if install_lock.is_some() {
+        let (_, size) = try!(paths::disk_usage(&install_dir));
+        entries.push(Entry {
+            last_use: try!(last_use::last_use(&install_dir)),
+            path: install_dir,
+            size: size,
+            contents_only: true,
+        });
+    }
+    entries.sort_by(|a, b| a.last_use.cmp(&b.last_use));
+
+    let mut total = entries.iter().fold(0, |sum, e| sum + e.size);
+    let mut removed = 0;
+    let mut freed = 0;
+    for entry in entries.iter() {
+        let age = now.saturating_sub(entry.last_use);
+        if age < GRACE_PERIOD {
+            break
+        }
+        let too_old = opts.max_age.map(|days| age >= days * DAY)
+                                  .unwrap_or(false);
+        let too_big = opts.max_size.map(|max| total > max).unwrap_or(false);
+        if !too_old && !too_big {
+            continue
+        }
+
+        if opts.dry_run {
+            try!(config.shell().say(entry.path.display(), BLACK));
+        } else {
+            try!(config.shell().verbose(|shell| {
+                shell.status("Removing", entry.path.display())
+            }));
+            try!(remove(&entry.path, entry.contents_only));
+        }
+        total -= entry.size;
+        removed += 1;
+        freed += entry.size;
+    }
+
+    if opts.dry_run {
+        try!(config.shell().say(format!("would remove {} cache entries \
+                                         totalling {} bytes", removed, freed),
+                                BLACK));
+    } else {
+        try!(config.shell().status("Removed", format!("{} cache entries \
+                                                       totalling {} bytes",
+                                                      removed, freed)));
+    }
+    Ok(())
+}
+
+// Collects the entries `depth` levels below `dir`, and cleans up the markers
+// of entries which have been removed by other means.
+fn collect(dir: &Path, depth: u32, now: u64, opts: &CacheGcOptions,
+           dst: &mut Vec<Entry>) -> CargoResult<()> {
+    if fs::metadata(dir).is_err() {
+        return Ok(())
+    }
+    for entry in try!(fs::read_dir(dir)) {
+        let path = try!(entry).path();
+        let name = path.file_name().and_then(|s| s.to_str()).unwrap_or("");
+        if name == last_use::MARKER_DIR {
+            if !opts.dry_run {
+                try!(remove_stale_markers(&path, now));
+            }
+        } else if name == flock::LOCK_FILE || name == flock::USE_LOCK_FILE {
+            continue
+        } else if depth > 1 {
+            if fs::metadata(&path).map(|m| m.is_dir()).unwrap_or(false) {
+                try!(collect(&path, depth - 1, now, opts, dst));
+            }
+        } else {
+            let (_, size) = try!(paths::disk_usage(&path));
+            dst.push(Entry {
+                last_use: try!(last_use::last_use(&path)),
+                path: path,
+                size: size,
+                contents_only: false,
+            });
+        }
+    }
+    Ok(())
+}
+
+fn remove_stale_markers(markers: &Path, now: u64) -> CargoResult<()> {
+    for marker in try!(fs::read_dir(markers)) {
+        let marker = try!(marker).path();
+        let entry = markers.parent().unwrap().join(marker.file_name().unwrap());
+        if fs::symlink_metadata(&entry).is_ok() {
+            continue
+        }
+        // The entry may be about to be created.
+        let age = now.saturating_sub(try!(last_use::last_use(&entry)));
+        if age >= GRACE_PERIOD {
+            try!(fs::remove_file(&marker));
+        }
+    }
+    Ok(())
+}
+
+fn remove(entry: &Path, contents_only: bool) -> CargoResult<()> {
+    let res = if contents_only {
+        // The lock files stay, as others may be waiting on them.
+        fs::read_dir(entry).and_then(|children| {
+            for child in children {
+                let child = try!(child).path();
+                let name = child.file_name().and_then(|s| s.to_str());
+                if name != Some(flock::LOCK_FILE) &&
+                   name != Some(flock::USE_LOCK_FILE) {
+                    try!(remove_path(&child));
+                }
+            }
+            Ok(())
+        })
+    } else {
+        remove_path(entry)
+    };
+    try!(res.chain_error(|| {
+        human(format!("failed to remove `{}`", entry.display()))
+    }));
+    // Removing the marker last means an entry which is only partially removed
+    // is still considered the next time around.
+    let _ = fs::remove_file(&last_use::marker(entry));
+    Ok(())
+}
+
+fn remove_path(path: &Path) -> io::Result<()> {
+    if try!(fs::symlink_metadata(path)).is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
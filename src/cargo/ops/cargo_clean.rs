@@ -1,36 +1,96 @@
+use std::collections::BTreeSet;
 use std::default::Default;
 use std::fs;
 use std::io::prelude::*;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+use term::color::BLACK;
 
 use core::{Package, PackageSet, Profiles, Profile};
+use core::registry::PackageRegistry;
+use core::resolver::Method;
 use core::source::{Source, SourceMap};
-use util::{CargoResult, human, ChainError, Config};
+use util::{CargoResult, human, ChainError, Config, paths};
 use ops::{self, Layout, Context, BuildConfig, Kind, Unit};
 
 pub struct CleanOptions<'a> {
     pub spec: &'a [String],
     pub target: Option<&'a str>,
     pub config: &'a Config,
+    /// Only clean the artifacts of the release profile.
+    pub release: bool,
+    /// Only clean documentation.
+    pub doc: bool,
+    /// List what would be removed instead of removing it.
+    pub dry_run: bool,
 }
 
 /// Cleans the project from build artifacts.
+///
+/// Unless `--release` or `--doc` restrict it, the artifacts of every profile
+/// and the documentation are cleaned.
 pub fn clean(manifest_path: &Path, opts: &CleanOptions) -> CargoResult<()> {
     let root = try!(Package::for_path(manifest_path, opts.config));
     let target_dir = opts.config.target_dir(&root);
 
+    let everything = !opts.release && !opts.doc;
+    let dests: &[&str] = if opts.release {
+        &["release"]
+    } else if everything {
+        &["debug", "release"]
+    } else {
+        &[]
+    };
+    let layouts = dests.iter().map(|dest| {
+        Layout::new(opts.config, &root, opts.target, dest)
+    }).collect::<Vec<_>>();
+    // The documentation lives next to the directories of the profiles.
+    let doc_layout = Layout::new(opts.config, &root, opts.target, "doc");
+    let docs = everything || opts.doc;
+
+    // Wait for builds into the target directory to finish, and keep new ones
+    // from starting, before removing anything. Documentation is built while
+    // holding the lock of the `debug` directory.
+    let mut locked = ["debug", "release"].iter().map(|dest| {
+        Layout::new(opts.config, &root, opts.target, dest)
+    }).filter(|layout| fs::metadata(layout.dest()).is_ok()).collect::<Vec<_>>();
+    for layout in locked.iter_mut() {
+        try!(layout.lock(opts.config));
+    }
+
+    let mut to_remove = Vec::new();
+
     // If we have a spec, then we need to delete some packages, otherwise, just
-    // remove the whole target directory and be done with it!
+    // remove the relevant directories and be done with it!
     if opts.spec.len() == 0 {
-        return rm_rf(&target_dir);
+        if everything {
+            // Without a target this is the whole target directory, including
+            // the output of any cross compilation.
+            match opts.target {
+                Some(..) => to_remove.push(doc_layout.dest().parent().unwrap()
+                                                     .to_path_buf()),
+                None => to_remove.push(target_dir),
+            }
+        } else {
+            to_remove.extend(layouts.iter().map(|l| l.dest().to_path_buf()));
+            if docs {
+                to_remove.push(doc_layout.dest().to_path_buf());
+            }
+        }
+        return remove(&to_remove, opts)
     }
 
-    // Load the lockfile (if one's available)
+    // Use the lockfile if one's available, and otherwise resolve the
+    // dependencies without writing one out.
     let lockfile = root.root().join("Cargo.lock");
     let source_id = root.package_id().source_id();
     let resolve = match try!(ops::load_lockfile(&lockfile, source_id)) {
         Some(resolve) => resolve,
-        None => return Err(human("A Cargo.lock must exist before cleaning"))
+        None => {
+            let mut registry = PackageRegistry::new(opts.config);
+            try!(ops::resolve_with_previous(&mut registry, &root,
+                                            Method::Everything, None, None))
+        }
     };
 
     // Create a compilation context to have access to information like target
@@ -38,9 +98,11 @@ pub fn clean(manifest_path: &Path, opts: &CleanOptions) -> CargoResult<()> {
     let srcs = SourceMap::new();
     let pkgs = PackageSet::new(&[]);
     let profiles = Profiles::default();
+    let mut build_config = BuildConfig::default();
+    build_config.requested_target = opts.target.map(|s| s.to_string());
     let cx = try!(Context::new(&resolve, &srcs, &pkgs, opts.config,
                                Layout::at(target_dir),
-                               None, BuildConfig::default(),
+                               None, build_config,
                                &profiles));
 
     // resolve package specs and remove the corresponding packages
@@ -55,26 +117,65 @@ pub fn clean(manifest_path: &Path, opts: &CleanOptions) -> CargoResult<()> {
         };
 
         // And finally, clean everything out!
-        for target in pkg.targets().iter() {
-            // TODO: `cargo clean --release`
-            let layout = Layout::new(opts.config, &root, opts.target, "debug");
-            try!(rm_rf(&layout.fingerprint(&pkg)));
-            let profiles = [Profile::default_dev(), Profile::default_test()];
-            for profile in profiles.iter() {
-                let unit = Unit {
-                    pkg: &pkg,
-                    target: target,
-                    profile: profile,
-                    kind: Kind::Target,
-                };
-                for filename in try!(cx.target_filenames(&unit)).iter() {
-                    try!(rm_rf(&layout.dest().join(&filename)));
-                    try!(rm_rf(&layout.deps().join(&filename)));
+        for layout in layouts.iter() {
+            to_remove.push(layout.fingerprint(&pkg));
+            to_remove.push(layout.build(&pkg));
+            for target in pkg.targets().iter() {
+                let profiles = [Profile::default_dev(), Profile::default_release(),
+                                Profile::default_test(), Profile::default_bench()];
+                for profile in profiles.iter() {
+                    let unit = Unit {
+                        pkg: &pkg,
+                        target: target,
+                        profile: profile,
+                        kind: Kind::Target,
+                    };
+                    for filename in try!(cx.target_filenames(&unit)).iter() {
+                        to_remove.push(layout.dest().join(&filename));
+                        to_remove.push(layout.deps().join(&filename));
+                        to_remove.push(layout.examples().join(&filename));
+                    }
                 }
             }
         }
+        if docs {
+            for target in pkg.targets().iter() {
+                let name = target.crate_name();
+                to_remove.push(doc_layout.dest().join(&name));
+                to_remove.push(doc_layout.dest().join("src").join(&name));
+            }
+        }
     }
 
+    remove(&to_remove, opts)
+}
+
+// Remove all of the given paths which exist, or only list them along with the
+// space they take up for a dry run.
+fn remove(to_remove: &[PathBuf], opts: &CleanOptions) -> CargoResult<()> {
+    // Several profiles produce the same file names, so the same path may be
+    // given more than once.
+    let to_remove = to_remove.iter().collect::<BTreeSet<_>>();
+    let mut files = 0;
+    let mut bytes = 0;
+    for path in to_remove.iter() {
+        if fs::symlink_metadata(path).is_err() {
+            continue
+        }
+        if !opts.dry_run {
+            try!(rm_rf(path));
+            continue
+        }
+        let (f, b) = try!(paths::disk_usage(path));
+        files += f;
+        bytes += b;
+        try!(opts.config.shell().say(path.display(), BLACK));
+    }
+    if opts.dry_run {
+        try!(opts.config.shell().say(format!("would remove {} files totalling \
+                                              {} bytes", files, bytes),
+                                     BLACK));
+    }
     Ok(())
 }
 
@@ -27,6 +27,8 @@ use std::default::Default;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+use glob::Pattern;
+
 use core::registry::PackageRegistry;
 use core::{Source, SourceId, PackageSet, Package, Target};
 use core::{Profile, TargetKind, Profiles};
@@ -34,6 +36,7 @@ use core::resolver::Method;
 use ops::{self, BuildOutput, ExecEngine};
 use util::config::{ConfigValue, Config};
 use util::{CargoResult, internal, human, ChainError, profile};
+use util::flock::{self, FileLock};
 
 /// Contains information about how a package should be compiled.
 pub struct CompileOptions<'a> {
@@ -65,6 +68,8 @@ pub struct CompileOptions<'a> {
 #[derive(Clone, Copy, PartialEq)]
 pub enum CompileMode {
     Test,
+    /// Only build what's needed to run the doc tests.
+    Doctest,
     Build,
     Bench,
     Doc { deps: bool },
@@ -74,13 +79,24 @@ pub enum CompileFilter<'a> {
     Everything,
     Only {
         lib: bool,
-        bins: &'a [String],
-        examples: &'a [String],
-        tests: &'a [String],
-        benches: &'a [String],
+        bins: FilterRule<'a>,
+        examples: FilterRule<'a>,
+        tests: FilterRule<'a>,
+        benches: FilterRule<'a>,
     }
 }
 
+/// Which targets of one kind a `CompileFilter::Only` selects.
+#[derive(Clone, Copy)]
+pub enum FilterRule<'a> {
+    /// All of the targets of the kind.
+    All,
+    /// The targets with these names.
+    Just(&'a [String]),
+    /// The targets whose names match any of these glob patterns.
+    Glob(&'a [String]),
+}
+
 pub fn compile<'a>(manifest_path: &Path,
                    options: &CompileOptions<'a>)
                    -> CargoResult<ops::Compilation<'a>> {
@@ -118,6 +134,10 @@ pub fn compile_pkg<'a>(root_package: &Package,
         return Err(human("jobs must be at least 1"))
     }
 
+    // The sources of the dependencies mustn't be removed from the caches
+    // while they're resolved, downloaded and compiled.
+    let _in_use = try!(lock_caches(config));
+
     let override_ids = try!(source_ids_from_config(options.config, root_package.root()));
 
     let (packages, resolve_with_overrides, sources) = {
@@ -154,17 +174,36 @@ pub fn compile_pkg<'a>(root_package: &Package,
         (packages, resolved_with_overrides, registry.move_sources())
     };
 
+    // Tests and benchmarks can be run for every package whose name matches a
+    // glob pattern.
+    let globs = match mode {
+        CompileMode::Test | CompileMode::Doctest | CompileMode::Bench => true,
+        CompileMode::Build | CompileMode::Doc { .. } => false,
+    };
     let mut invalid_spec = vec![];
-    let pkgids = if spec.len() > 0 {
-        spec.iter().filter_map(|p| {
+    let mut pkgids = Vec::new();
+    for p in spec.iter() {
+        if globs && p.contains(&['*', '?', '['][..]) {
+            let pattern = try!(Pattern::new(p).map_err(|e| {
+                human(format!("could not parse pattern `{}`: {}", p, e))
+            }));
+            let before = pkgids.len();
+            pkgids.extend(resolve_with_overrides.iter().filter(|id| {
+                pattern.matches(id.name())
+            }));
+            if pkgids.len() == before {
+                invalid_spec.push(p.to_string());
+            }
+        } else {
             match resolve_with_overrides.query(&p) {
-                Ok(p) => Some(p),
-                Err(..) => { invalid_spec.push(p.to_string()); None }
+                Ok(p) => pkgids.push(p),
+                Err(..) => invalid_spec.push(p.to_string()),
             }
-        }).collect::<Vec<_>>()
-    } else {
-        vec![root_package.package_id()]
-    };
+        }
+    }
+    if spec.len() == 0 {
+        pkgids.push(root_package.package_id());
+    }
 
     if spec.len() > 0 && invalid_spec.len() > 0 {
         return Err(human(format!("could not find package matching spec `{}`",
@@ -215,7 +254,7 @@ pub fn compile_pkg<'a>(root_package: &Package,
         }
     }
 
-    let mut ret = {
+    let ret = {
         let _p = profile::start("compiling");
         let mut build_config = try!(scrape_build_config(config, jobs, target));
         build_config.exec_engine = exec_engine.clone();
@@ -223,6 +262,7 @@ pub fn compile_pkg<'a>(root_package: &Package,
         if let CompileMode::Doc { deps } = mode {
             build_config.doc_all = deps;
         }
+        build_config.doc_test = mode == CompileMode::Doctest;
 
         try!(ops::compile_targets(&package_targets,
                                   &PackageSet::new(&packages),
@@ -234,33 +274,73 @@ pub fn compile_pkg<'a>(root_package: &Package,
                                   ))
     };
 
-    ret.to_doc_test = to_builds.iter().map(|&p| p.clone()).collect();
-
     return Ok(ret);
 }
 
+/// Marks the sources in the global caches as in use until the returned locks
+/// are dropped, see `cargo cache gc`.
+fn lock_caches(config: &Config) -> CargoResult<Vec<FileLock>> {
+    Ok(vec![
+        try!(flock::in_use(&config.registry_cache_path(), "the package cache",
+                           config)),
+        try!(flock::in_use(&config.registry_source_path(),
+                           "the package sources", config)),
+        try!(flock::in_use(&config.git_db_path(), "the git database", config)),
+        try!(flock::in_use(&config.git_checkout_path(), "the git checkouts",
+                           config)),
+    ])
+}
+
 impl<'a> CompileFilter<'a> {
     pub fn new(lib_only: bool,
-               bins: &'a [String],
-               tests: &'a [String],
-               examples: &'a [String],
-               benches: &'a [String]) -> CompileFilter<'a> {
+               bins: &'a [String], all_bins: bool,
+               tests: &'a [String], all_tests: bool,
+               examples: &'a [String], all_examples: bool,
+               benches: &'a [String], all_benches: bool) -> CompileFilter<'a> {
+        let rule = |names: &'a [String], all: bool| {
+            if all {FilterRule::All} else {FilterRule::Just(names)}
+        };
         if lib_only || !bins.is_empty() || !tests.is_empty() ||
-           !examples.is_empty() || !benches.is_empty() {
+           !examples.is_empty() || !benches.is_empty() ||
+           all_bins || all_tests || all_examples || all_benches {
             CompileFilter::Only {
-                lib: lib_only, bins: bins, examples: examples, benches: benches,
-                tests: tests,
+                lib: lib_only,
+                bins: rule(bins, all_bins),
+                examples: rule(examples, all_examples),
+                benches: rule(benches, all_benches),
+                tests: rule(tests, all_tests),
             }
         } else {
             CompileFilter::Everything
         }
     }
 
+    /// Treat the names given for each kind of target as glob patterns, which
+    /// `cargo test` and `cargo bench` accept.
+    pub fn with_patterns(self) -> CompileFilter<'a> {
+        let glob = |rule: FilterRule<'a>| match rule {
+            FilterRule::Just(names) => FilterRule::Glob(names),
+            rule => rule,
+        };
+        match self {
+            CompileFilter::Everything => CompileFilter::Everything,
+            CompileFilter::Only { lib, bins, examples, tests, benches } => {
+                CompileFilter::Only {
+                    lib: lib,
+                    bins: glob(bins),
+                    examples: glob(examples),
+                    tests: glob(tests),
+                    benches: glob(benches),
+                }
+            }
+        }
+    }
+
     pub fn matches(&self, target: &Target) -> bool {
         match *self {
             CompileFilter::Everything => true,
             CompileFilter::Only { lib, bins, examples, tests, benches } => {
-                let list = match *target.kind() {
+                let rule = match *target.kind() {
                     TargetKind::Bin => bins,
                     TargetKind::Test => tests,
                     TargetKind::Bench => benches,
@@ -268,7 +348,23 @@ impl<'a> CompileFilter<'a> {
                     TargetKind::Lib(..) => return lib,
                     TargetKind::CustomBuild => return false,
                 };
-                list.iter().any(|x| *x == target.name())
+                rule.matches(target.name())
+            }
+        }
+    }
+}
+
+impl<'a> FilterRule<'a> {
+    /// Whether a target named `name` is selected. Patterns which aren't valid
+    /// globs select nothing here, they're reported by `generate_targets`.
+    pub fn matches(&self, name: &str) -> bool {
+        match *self {
+            FilterRule::All => true,
+            FilterRule::Just(names) => names.iter().any(|n| n == name),
+            FilterRule::Glob(patterns) => {
+                patterns.iter().any(|p| {
+                    Pattern::new(p).map(|p| p.matches(name)).unwrap_or(false)
+                })
             }
         }
     }
@@ -285,7 +381,7 @@ fn generate_targets<'a>(pkg: &'a Package,
     let build = if release {&profiles.release} else {&profiles.dev};
     let test = if release {&profiles.bench} else {&profiles.test};
     let profile = match mode {
-        CompileMode::Test => test,
+        CompileMode::Test | CompileMode::Doctest => test,
         CompileMode::Bench => &profiles.bench,
         CompileMode::Build => build,
         CompileMode::Doc { .. } => &profiles.doc,
@@ -314,6 +410,10 @@ fn generate_targets<'a>(pkg: &'a Package,
                     }
                     Ok(base)
                 }
+                CompileMode::Doctest => {
+                    Ok(pkg.targets().iter().filter(|t| t.doctested())
+                          .map(|t| (t, build)).collect())
+                }
                 CompileMode::Build => {
                     Ok(pkg.targets().iter().filter(|t| {
                         t.is_bin() || t.is_lib()
@@ -337,19 +437,49 @@ fn generate_targets<'a>(pkg: &'a Package,
             }
 
             {
-                let mut find = |names: &[String], desc, kind, profile| {
+                let mut find = |rule: FilterRule, desc, kind, profile| {
+                    let names = match rule {
+                        FilterRule::All => {
+                            targets.extend(pkg.targets().iter().filter(|t| {
+                                *t.kind() == kind
+                            }).map(|t| (t, profile)));
+                            return Ok(())
+                        }
+                        FilterRule::Just(names) |
+                        FilterRule::Glob(names) => names,
+                    };
                     for name in names {
-                        let target = pkg.targets().iter().find(|t| {
-                            t.name() == *name && *t.kind() == kind
-                        });
-                        let t = match target {
-                            Some(t) => t,
-                            None => return Err(human(format!("no {} target \
-                                                              named `{}`",
-                                                             desc, name))),
+                        let pattern = match rule {
+                            FilterRule::Glob(..) => {
+                                Some(try!(Pattern::new(name).map_err(|e| {
+                                    human(format!("could not parse pattern \
+                                                   `{}`: {}", name, e))
+                                })))
+                            }
+                            _ => None,
                         };
-                        debug!("found {} `{}`", desc, name);
-                        targets.push((t, profile));
+                        let mut found = false;
+                        for t in pkg.targets().iter().filter(|t| {
+                            *t.kind() == kind && match pattern {
+                                Some(ref p) => p.matches(t.name()),
+                                None => t.name() == &name[..],
+                            }
+                        }) {
+                            debug!("found {} `{}`", desc, t.name());
+                            found = true;
+                            if !targets.contains(&(t, profile)) {
+                                targets.push((t, profile));
+                            }
+                        }
+                        if !found && pattern.is_some() &&
+                           name.contains(&['*', '?', '['][..]) {
+                            return Err(human(format!("no {} target matches \
+                                                      the pattern `{}`",
+                                                     desc, name)))
+                        } else if !found {
+                            return Err(human(format!("no {} target named \
+                                                      `{}`", desc, name)))
+                        }
                     }
                     Ok(())
                 };
@@ -396,11 +526,29 @@ fn source_ids_from_config(config: &Config, cur_path: &Path)
 /// * build.jobs
 /// * target.$target.ar
 /// * target.$target.linker
+/// * target.$target.runner
 /// * target.$target.libfoo.metadata
 fn scrape_build_config(config: &Config,
                        jobs: Option<u32>,
                        target: Option<String>)
                        -> CargoResult<ops::BuildConfig> {
+    let jobs = try!(build_jobs(config, jobs));
+    let mut base = ops::BuildConfig {
+        jobs: jobs,
+        requested_target: target.clone(),
+        ..Default::default()
+    };
+    base.host = try!(scrape_target_config(config, &config.rustc_info().host));
+    base.target = match target.as_ref() {
+        Some(triple) => try!(scrape_target_config(config, &triple)),
+        None => base.host.clone(),
+    };
+    Ok(base)
+}
+
+/// The number of jobs to run in parallel, which is either given explicitly or
+/// configured with `build.jobs`, and otherwise the number of CPUs.
+pub fn build_jobs(config: &Config, jobs: Option<u32>) -> CargoResult<u32> {
     let cfg_jobs = match try!(config.get_i64("build.jobs")) {
         Some((n, p)) => {
             if n <= 0 {
@@ -415,18 +563,7 @@ fn scrape_build_config(config: &Config,
         }
         None => None,
     };
-    let jobs = jobs.or(cfg_jobs).unwrap_or(::num_cpus::get() as u32);
-    let mut base = ops::BuildConfig {
-        jobs: jobs,
-        requested_target: target.clone(),
-        ..Default::default()
-    };
-    base.host = try!(scrape_target_config(config, &config.rustc_info().host));
-    base.target = match target.as_ref() {
-        Some(triple) => try!(scrape_target_config(config, &triple)),
-        None => base.host.clone(),
-    };
-    Ok(base)
+    Ok(jobs.or(cfg_jobs).unwrap_or(::num_cpus::get() as u32))
 }
 
 fn scrape_target_config(config: &Config, triple: &str)
@@ -436,6 +573,7 @@ fn scrape_target_config(config: &Config, triple: &str)
     let mut ret = ops::TargetConfig {
         ar: try!(config.get_path(&format!("{}.ar", key))),
         linker: try!(config.get_path(&format!("{}.linker", key))),
+        runner: try!(config.get_program(&format!("{}.runner", key))),
         overrides: HashMap::new(),
     };
     let table = match try!(config.get_table(&key)) {
@@ -443,7 +581,9 @@ fn scrape_target_config(config: &Config, triple: &str)
         None => return Ok(ret),
     };
     for (lib_name, _) in table.into_iter() {
-        if lib_name == "ar" || lib_name == "linker" { continue }
+        if lib_name == "ar" || lib_name == "linker" || lib_name == "runner" {
+            continue
+        }
 
         let mut output = BuildOutput {
             library_paths: Vec::new(),
@@ -1,3 +1,4 @@
+use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
 use std::path::Path;
 
@@ -14,6 +15,21 @@ pub struct UpdateOptions<'a> {
     pub to_update: &'a [String],
     pub precise: Option<&'a str>,
     pub aggressive: bool,
+    /// Compute and report the changes without writing `Cargo.lock`.
+    pub dry_run: bool,
+}
+
+/// A single difference between the previous and the updated lockfile.
+#[derive(RustcEncodable)]
+pub struct LockfileChange {
+    pub name: String,
+    /// One of `added`, `removed`, `upgraded`, `downgraded` or
+    /// `source-changed`.
+    pub kind: &'static str,
+    pub old_version: Option<String>,
+    pub new_version: Option<String>,
+    pub old_source: Option<String>,
+    pub new_source: Option<String>,
 }
 
 pub fn generate_lockfile(manifest_path: &Path, config: &Config)
@@ -27,8 +43,13 @@ pub fn generate_lockfile(manifest_path: &Path, config: &Config)
     Ok(())
 }
 
+/// Update the lockfile of the package at `manifest_path`, returning the list
+/// of changes relative to the previous lockfile.
+///
+/// In a dry run the changes are only reported and `Cargo.lock` is left
+/// untouched.
 pub fn update_lockfile(manifest_path: &Path,
-                       opts: &UpdateOptions) -> CargoResult<()> {
+                       opts: &UpdateOptions) -> CargoResult<Vec<LockfileChange>> {
     let package = try!(Package::for_path(manifest_path, opts.config));
 
     let previous_resolve = match try!(ops::load_pkg_lockfile(&package)) {
@@ -82,34 +103,69 @@ pub fn update_lockfile(manifest_path: &Path,
                                                   Some(&previous_resolve),
                                                   Some(&to_avoid)));
 
+    let changes = diff_resolves(&previous_resolve, &resolve);
+
     // Summarize what is changing for the user.
     let print_change = |status: &str, msg: String| {
         opts.config.shell().status(status, msg)
     };
-    for (removed, added) in compare_dependency_graphs(&previous_resolve, &resolve) {
-        if removed.len() == 1 && added.len() == 1 {
-            if removed[0].source_id().is_git() {
-                try!(print_change("Updating", format!("{} -> #{}",
-                    removed[0],
-                    &added[0].source_id().precise().unwrap()[..8])));
-            } else {
-                try!(print_change("Updating", format!("{} -> v{}",
-                    removed[0],
-                    added[0].version())));
-            }
+    if opts.dry_run {
+        for &(kind, old, new) in changes.iter() {
+            let (status, msg) = match (kind, old, new) {
+                ("upgraded", Some(old), Some(new)) => {
+                    ("Upgrading", format!("{} -> v{}", old, new.version()))
+                }
+                ("downgraded", Some(old), Some(new)) => {
+                    ("Downgrading", format!("{} -> v{}", old, new.version()))
+                }
+                ("source-changed", Some(old), Some(new)) => {
+                    ("Updating", format!("{} -> {}", old, new.source_id()))
+                }
+                (_, Some(old), None) => ("Removing", old.to_string()),
+                (_, None, Some(new)) => ("Adding", new.to_string()),
+                _ => unreachable!(),
+            };
+            try!(print_change(status, msg));
         }
-        else {
-            for package in removed.iter() {
-                try!(print_change("Removing", format!("{}", package)));
+        try!(opts.config.shell().warn("warning: not updating Cargo.lock \
+                                       due to dry run"));
+    } else {
+        for (removed, added) in compare_dependency_graphs(&previous_resolve,
+                                                          &resolve) {
+            if removed.len() == 1 && added.len() == 1 {
+                if removed[0].source_id().is_git() {
+                    try!(print_change("Updating", format!("{} -> #{}",
+                        removed[0],
+                        &added[0].source_id().precise().unwrap()[..8])));
+                } else {
+                    try!(print_change("Updating", format!("{} -> v{}",
+                        removed[0],
+                        added[0].version())));
+                }
             }
-            for package in added.iter() {
-                try!(print_change("Adding", format!("{}", package)));
+            else {
+                for package in removed.iter() {
+                    try!(print_change("Removing", format!("{}", package)));
+                }
+                for package in added.iter() {
+                    try!(print_change("Adding", format!("{}", package)));
+                }
             }
         }
+
+        try!(ops::write_pkg_lockfile(&package, &resolve));
     }
 
-    try!(ops::write_pkg_lockfile(&package, &resolve));
-    return Ok(());
+    return Ok(changes.into_iter().map(|(kind, old, new)| {
+        LockfileChange {
+            name: old.or(new).unwrap().name().to_string(),
+            kind: kind,
+            old_version: old.map(|p| p.version().to_string()),
+            new_version: new.map(|p| p.version().to_string()),
+            old_source: old.map(|p| p.source_id().to_url()),
+            new_source: new.map(|p| p.source_id().to_url()),
+        }
+    }).collect());
 
     fn fill_with_deps<'a>(resolve: &'a Resolve, dep: &'a PackageId,
                           set: &mut HashSet<&'a PackageId>,
@@ -167,4 +223,47 @@ pub fn update_lockfile(manifest_path: &Path,
         packages.sort();
         packages.iter().map(|k| changes[k].clone()).collect()
     }
+
+    // Pair up the packages of two resolves by name, classifying each
+    // difference between them. Packages whose version and source (including
+    // the precise revision) are unchanged are not reported.
+    fn diff_resolves<'a>(previous_resolve: &'a Resolve, resolve: &'a Resolve)
+                         -> Vec<(&'static str, Option<&'a PackageId>,
+                                 Option<&'a PackageId>)> {
+        fn same(a: &PackageId, b: &PackageId) -> bool {
+            a.version() == b.version() &&
+                a.source_id().to_url() == b.source_id().to_url()
+        }
+
+        let mut by_name = HashMap::new();
+        for dep in previous_resolve.iter() {
+            by_name.entry(dep.name()).or_insert((vec![], vec![])).0.push(dep);
+        }
+        for dep in resolve.iter() {
+            by_name.entry(dep.name()).or_insert((vec![], vec![])).1.push(dep);
+        }
+        let mut by_name = by_name.into_iter().collect::<Vec<_>>();
+        by_name.sort_by(|a, b| a.0.cmp(b.0));
+
+        let mut ret = Vec::new();
+        for (_, (old, new)) in by_name.into_iter() {
+            let removed = old.iter().filter(|a| !new.iter().any(|b| same(a, b)))
+                             .map(|a| *a).collect::<Vec<_>>();
+            let added = new.iter().filter(|b| !old.iter().any(|a| same(a, b)))
+                           .map(|b| *b).collect::<Vec<_>>();
+            if removed.len() == 1 && added.len() == 1 {
+                let (old, new) = (removed[0], added[0]);
+                let kind = match old.version().cmp(new.version()) {
+                    Ordering::Less => "upgraded",
+                    Ordering::Greater => "downgraded",
+                    Ordering::Equal => "source-changed",
+                };
+                ret.push((kind, Some(old), Some(new)));
+                continue
+            }
+            ret.extend(removed.into_iter().map(|p| ("removed", Some(p), None)));
+            ret.extend(added.into_iter().map(|p| ("added", None, Some(p))));
+        }
+        ret
+    }
 }
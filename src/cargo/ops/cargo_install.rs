@@ -1,22 +1,26 @@
 use std::collections::btree_map::Entry;
-use std::collections::{BTreeMap, BTreeSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::env;
 use std::ffi::OsString;
 use std::fs::{self, File};
 use std::io::prelude::*;
 use std::path::{Path, PathBuf};
 
+use rustc_serialize::{Decodable, Encodable};
+use term::color::BLACK;
 use toml;
 
 use core::{SourceId, Source, Package, Registry, Dependency, PackageIdSpec};
 use core::PackageId;
 use ops::{self, CompileFilter};
 use sources::{GitSource, PathSource, RegistrySource};
-use util::{CargoResult, ChainError, Config, human, internal};
+use util::{CargoError, CargoResult, ChainError, Config, human, internal};
+use util::{flock, last_use};
 
 #[derive(RustcDecodable, RustcEncodable)]
 enum CrateListing {
     V1(CrateListingV1),
+    V2(CrateListingV2),
 }
 
 #[derive(RustcDecodable, RustcEncodable)]
@@ -24,6 +28,30 @@ struct CrateListingV1 {
     v1: BTreeMap<PackageId, BTreeSet<String>>,
 }
 
+#[derive(RustcDecodable, RustcEncodable)]
+struct CrateListingV2 {
+    v2: BTreeMap<PackageId, InstalledCrate>,
+}
+
+/// The binaries installed for a package and how they were built.
+#[derive(RustcDecodable, RustcEncodable, Clone)]
+struct InstalledCrate {
+    bins: BTreeSet<String>,
+    features: Vec<String>,
+    no_default_features: bool,
+    profile: String,
+    target: Option<String>,
+    rustc: Option<String>,
+}
+
+/// A crate which was built, with its binaries copied next to where they'll be
+/// installed, waiting for the other crates of the same invocation to build.
+struct Staged {
+    pkg: Package,
+    bins: Vec<(String, PathBuf)>,
+    replaced: Vec<(String, Option<PackageId>)>,
+}
+
 struct Transaction {
     bins: Vec<PathBuf>,
 }
@@ -37,12 +65,103 @@ impl Drop for Transaction {
 }
 
 pub fn install(root: Option<&str>,
-               krate: Option<&str>,
+               krates: Vec<&str>,
                source_id: &SourceId,
                vers: Option<&str>,
+               force: bool,
                opts: &ops::CompileOptions) -> CargoResult<()> {
     let config = opts.config;
     let root = try!(resolve_root(root, config));
+    let dst = root.join("bin");
+    let mut list = try!(read_crate_list(&root));
+
+    // Binaries which are replaced aren't tracked by the transaction, as
+    // removing them on failure would lose the previous installation.
+    let mut t = Transaction { bins: Vec::new() };
+
+    let staged = if krates.len() <= 1 {
+        let krate = krates.into_iter().next();
+        vec![try!(stage(&root, &list, &[], krate, source_id, vers, force,
+                        opts, &mut t))]
+    } else {
+        if vers.is_some() {
+            return Err(human("cannot specify a version with --vers when \
+                              installing multiple crates"))
+        }
+
+        // Every crate is built before any of them is installed, so that
+        // nothing is installed unless all of them can be. Keep going when a
+        // crate fails so every failure can be reported at the end. Crates
+        // from git are all taken from the revision the first one was found
+        // at.
+        let mut source_id = source_id.clone();
+        let mut staged = Vec::new();
+        let mut failed = Vec::new();
+        let requested = krates.len();
+        for krate in krates {
+            let krate_staged = match stage(&root, &list, &staged, Some(krate),
+                                           &source_id, None, force, opts,
+                                           &mut t) {
+                Ok(krate_staged) => krate_staged,
+                Err(e) => { failed.push((krate, e)); continue }
+            };
+            if source_id.is_git() {
+                source_id = krate_staged.pkg.package_id().source_id().clone();
+            }
+            staged.push(krate_staged);
+        }
+
+        if !failed.is_empty() {
+            for &(krate, ref e) in failed.iter() {
+                try!(config.shell().error(format!("failed to install `{}`: {}",
+                                                  krate, error_chain(&**e))));
+            }
+            let names = failed.iter().map(|&(k, _)| format!("`{}`", k))
+                              .collect::<Vec<_>>();
+            return Err(human(format!("failed to install {} of the {} requested \
+                                      crates, so none were installed: {}",
+                                     names.len(), requested,
+                                     join(&names, ", "))))
+        }
+        staged
+    };
+
+    for krate_staged in staged.into_iter() {
+        try!(install_staged(&dst, &mut list, krate_staged, opts, &mut t));
+    }
+
+    try!(write_crate_list(&root, list));
+    t.bins.truncate(0);
+
+    // Print a warning that if this directory isn't in PATH that they won't be
+    // able to run these commands.
+    let path = env::var_os("PATH").unwrap_or(OsString::new());
+    for path in env::split_paths(&path) {
+        if path == dst {
+            return Ok(())
+        }
+    }
+
+    try!(config.shell().warn(&format!("be sure to add `{}` to your PATH to be \
+                                       able to run the installed binaries",
+                                      dst.display())));
+    Ok(())
+}
+
+// Build a single crate and copy its binaries next to their destination.
+// The copies are tracked by `t`, so they're removed again unless they're
+// installed. `staged` are the crates built before this one by the same
+// invocation, which mustn't have binaries of the same name.
+fn stage(root: &Path,
+         list: &CrateListingV2,
+         staged: &[Staged],
+         krate: Option<&str>,
+         source_id: &SourceId,
+         vers: Option<&str>,
+         force: bool,
+         opts: &ops::CompileOptions,
+         t: &mut Transaction) -> CargoResult<Staged> {
+    let config = opts.config;
     let (pkg, source) = if source_id.is_git() {
         try!(select_pkg(GitSource::new(source_id, config), source_id,
                         krate, vers, &mut |git| git.read_packages()))
@@ -59,54 +178,129 @@ pub fn install(root: Option<&str>,
                                             crates.io"))))
     };
 
-    let mut list = try!(read_crate_list(&root));
     let dst = root.join("bin");
-    try!(check_overwrites(&dst, &pkg, &opts.filter, &list));
+    let replaced = try!(check_overwrites(&dst, &pkg, &opts.filter, list,
+                                         force));
 
-    let target_dir = config.cwd().join("target-install");
+    let target_dir = try!(install_target_dir(config));
+    // Keeps `cargo cache gc` from cleaning the directory out while building
+    // in it.
+    let _in_use = try!(flock::in_use(&target_dir, "the install target \
+                                                   directory", config));
+    try!(last_use::record(&target_dir));
     config.set_target_dir(&target_dir);
     let compile = try!(ops::compile_pkg(&pkg, Some(source), opts).chain_error(|| {
         human(format!("failed to compile `{}`, intermediate artifacts can be \
                        found at `{}`", pkg, target_dir.display()))
     }));
 
-    let mut t = Transaction { bins: Vec::new() };
+    let mut bins = Vec::new();
     try!(fs::create_dir_all(&dst));
     for bin in compile.binaries.iter() {
-        let dst = dst.join(bin.file_name().unwrap());
-        try!(config.shell().status("Installing", dst.display()));
-        try!(fs::copy(&bin, &dst).chain_error(|| {
+        let name = bin.file_name().unwrap().to_string_lossy().into_owned();
+        for other in staged.iter() {
+            if other.bins.iter().any(|&(ref n, _)| *n == name) {
+                return Err(human(format!("binary `{}` is also installed by \
+                                          `{}`", name, other.pkg)))
+            }
+        }
+        // Copying next to the destination allows installing with a rename,
+        // so an interrupted install never leaves a truncated binary behind.
+        let tmp = dst.join(format!(".{}.cargo-install", name));
+        t.bins.push(tmp.clone());
+        try!(fs::copy(&bin, &tmp).chain_error(|| {
             human(format!("failed to copy `{}` to `{}`", bin.display(),
-                          dst.display()))
+                          tmp.display()))
         }));
-        t.bins.push(dst);
+        bins.push((name, tmp));
     }
-    try!(fs::remove_dir_all(&target_dir));
 
-    list.v1.entry(pkg.package_id().clone()).or_insert_with(|| {
-        BTreeSet::new()
-    }).extend(t.bins.iter().map(|t| {
-        t.file_name().unwrap().to_string_lossy().into_owned()
-    }));
-    try!(write_crate_list(&root, list));
+    Ok(Staged { pkg: pkg, bins: bins, replaced: replaced })
+}
 
-    t.bins.truncate(0);
+// Move the binaries of a staged crate into place, recording them in `list`.
+// The binaries which didn't exist before are tracked by `t`, so they're
+// removed again should the overall installation fail.
+fn install_staged(dst: &Path,
+                  list: &mut CrateListingV2,
+                  staged: Staged,
+                  opts: &ops::CompileOptions,
+                  t: &mut Transaction) -> CargoResult<()> {
+    let config = opts.config;
+    let mut installed = BTreeSet::new();
+    for (name, tmp) in staged.bins.into_iter() {
+        let dst = dst.join(&name);
+        let exists = fs::metadata(&dst).is_ok();
+        let status = if exists {"Replacing"} else {"Installing"};
+        try!(config.shell().status(status, dst.display()));
+        try!(fs::rename(&tmp, &dst).chain_error(|| {
+            human(format!("failed to move `{}` to `{}`", tmp.display(),
+                          dst.display()))
+        }));
+        if !exists {
+            t.bins.push(dst);
+        }
+        installed.insert(name);
+    }
 
-    // Print a warning that if this directory isn't in PATH that they won't be
-    // able to run these commands.
-    let path = env::var_os("PATH").unwrap_or(OsString::new());
-    for path in env::split_paths(&path) {
-        if path == dst {
-            return Ok(())
+    // Binaries which were replaced no longer belong to the package they were
+    // previously installed with.
+    for &(ref bin, ref owner) in staged.replaced.iter() {
+        let owner = match *owner {
+            Some(ref owner) => owner,
+            None => continue,
+        };
+        let empty = match list.v2.get_mut(owner) {
+            Some(prev) => { prev.bins.remove(bin); prev.bins.is_empty() }
+            None => false,
+        };
+        if empty {
+            list.v2.remove(owner);
         }
     }
 
-    try!(config.shell().warn(&format!("be sure to add `{}` to your PATH to be \
-                                       able to run the installed binaries",
-                                      dst.display())));
+    let rustc = config.rustc_info().verbose_version.lines().next()
+                      .map(|s| s.to_string());
+    let entry = list.v2.entry(staged.pkg.package_id().clone())
+                       .or_insert_with(|| {
+        InstalledCrate {
+            bins: BTreeSet::new(),
+            features: Vec::new(),
+            no_default_features: false,
+            profile: String::new(),
+            target: None,
+            rustc: None,
+        }
+    });
+    entry.bins.extend(installed.into_iter());
+    entry.features = opts.features.to_vec();
+    entry.no_default_features = opts.no_default_features;
+    entry.profile = (if opts.release {"release"} else {"dev"}).to_string();
+    entry.target = opts.target.map(|s| s.to_string());
+    entry.rustc = rustc;
     Ok(())
 }
 
+// An error message followed by its human readable causes, as they'd be
+// printed if the error were returned from the command.
+fn error_chain(err: &CargoError) -> String {
+    let mut msg = err.to_string();
+    let mut cause = err.cargo_cause();
+    while let Some(e) = cause {
+        if !e.is_human() {
+            break
+        }
+        msg.push_str(&format!("\n\nCaused by:\n  {}", e));
+        cause = e.cargo_cause();
+    }
+    msg
+}
+
+#[allow(deprecated)] // connect => join in 1.3
+fn join(strs: &[String], sep: &str) -> String {
+    strs.connect(sep)
+}
+
 fn select_pkg<'a, T>(mut source: T,
                      source_id: &SourceId,
                      name: Option<&str>,
@@ -180,91 +374,213 @@ fn one<I, F>(mut i: I, f: F) -> CargoResult<Option<I::Item>>
     }
 }
 
+// Check that installing `pkg` won't clobber binaries of other packages,
+// returning the binaries which will be replaced along with the package they
+// were previously installed as part of.
+//
+// Binaries may always be replaced when `force` is set, or when they belong
+// to an older installation of the same package.
 fn check_overwrites(dst: &Path,
                     pkg: &Package,
                     filter: &ops::CompileFilter,
-                    prev: &CrateListingV1) -> CargoResult<()> {
-    let check = |name| {
-        let name = format!("{}{}", name, env::consts::EXE_SUFFIX);
-        if fs::metadata(dst.join(&name)).is_err() {
-            return Ok(())
-        }
-        let mut msg = format!("binary `{}` already exists in destination", name);
-        if let Some((p, _)) = prev.v1.iter().find(|&(_, v)| v.contains(&name)) {
-            msg.push_str(&format!(" as part of `{}`", p));
-        }
-        Err(human(msg))
-    };
-    match *filter {
-        CompileFilter::Everything => {
-            // If explicit --bin or --example flags were passed then those'll
-            // get checked during cargo_compile, we only care about the "build
-            // everything" case here
-            if pkg.targets().iter().filter(|t| t.is_bin()).next().is_none() {
-                return Err(human("specified package has no binaries"))
+                    prev: &CrateListingV2,
+                    force: bool)
+                    -> CargoResult<Vec<(String, Option<PackageId>)>> {
+    let mut replaced = Vec::new();
+    {
+        let mut check = |name: &str| -> CargoResult<()> {
+            let name = format!("{}{}", name, env::consts::EXE_SUFFIX);
+            if fs::metadata(dst.join(&name)).is_err() {
+                return Ok(())
             }
+            let owner = prev.v2.iter().find(|&(_, v)| v.bins.contains(&name))
+                                      .map(|(p, _)| p.clone());
+            let upgrade = owner.as_ref().map_or(false, |p| {
+                is_upgrade(p, pkg.package_id())
+            });
+            if !force && !upgrade {
+                let mut msg = format!("binary `{}` already exists in \
+                                       destination", name);
+                if let Some(ref p) = owner {
+                    msg.push_str(&format!(" as part of `{}`", p));
+                }
+                msg.push_str("\nAdd --force to overwrite");
+                return Err(human(msg))
+            }
+            replaced.push((name, owner));
+            Ok(())
+        };
+        match *filter {
+            CompileFilter::Everything => {
+                // If explicit --bin or --example flags were passed then
+                // those'll get checked during cargo_compile, we only care
+                // about the "build everything" case here
+                if pkg.targets().iter().filter(|t| t.is_bin()).next().is_none() {
+                    return Err(human("specified package has no binaries"))
+                }
 
-            for target in pkg.targets().iter().filter(|t| t.is_bin()) {
-                try!(check(target.name()));
+                for target in pkg.targets().iter().filter(|t| t.is_bin()) {
+                    try!(check(target.name()));
+                }
             }
-        }
-        CompileFilter::Only { bins, examples, .. } => {
-            for bin in bins.iter().chain(examples) {
-                try!(check(bin));
+            CompileFilter::Only { .. } => {
+                for target in pkg.targets().iter().filter(|t| {
+                    (t.is_bin() || t.is_example()) && filter.matches(t)
+                }) {
+                    try!(check(target.name()));
+                }
             }
         }
     }
-    Ok(())
+    Ok(replaced)
+}
+
+// Whether `new` is a newer installation of the same package as `old`. Git
+// packages rarely bump their version, so any other revision counts as well.
+fn is_upgrade(old: &PackageId, new: &PackageId) -> bool {
+    old.name() == new.name() && old.source_id() == new.source_id() &&
+        (old.version() < new.version() ||
+         (new.source_id().is_git() &&
+          old.source_id().precise() != new.source_id().precise()))
 }
 
-fn read_crate_list(path: &Path) -> CargoResult<CrateListingV1> {
+// The installed binaries are listed in `.crates.toml` in the format every
+// version of Cargo understands, so that older versions sharing the same root
+// keep working. How each package was built is recorded separately in
+// `.crates2.toml`, and packages missing from it (such as those installed by
+// older versions) are assumed to have been built with the default options.
+fn read_crate_list(path: &Path) -> CargoResult<CrateListingV2> {
     let metadata = path.join(".crates.toml");
-    let mut f = match File::open(&metadata) {
-        Ok(f) => f,
-        Err(..) => return Ok(CrateListingV1 { v1: BTreeMap::new() }),
-    };
-    (|| -> CargoResult<_> {
-        let mut contents = String::new();
-        try!(f.read_to_string(&mut contents));
-        let listing = try!(toml::decode_str(&contents).chain_error(|| {
-            internal("invalid TOML found for metadata")
-        }));
-        match listing {
-            CrateListing::V1(v1) => Ok(v1),
-        }
-    }).chain_error(|| {
+    let listing = try!(read_toml::<CrateListing>(&metadata).chain_error(|| {
         human(format!("failed to parse crate metadata at `{}`",
                       metadata.display()))
+    }));
+    let bins = match listing {
+        None => return Ok(CrateListingV2 { v2: BTreeMap::new() }),
+        Some(CrateListing::V1(v1)) => v1.v1,
+        // Written by versions which kept everything in `.crates.toml`.
+        Some(CrateListing::V2(v2)) => return Ok(v2),
+    };
+
+    let details_path = path.join(".crates2.toml");
+    let details = try!(read_toml::<CrateListingV2>(&details_path).chain_error(|| {
+        human(format!("failed to parse crate metadata at `{}`",
+                      details_path.display()))
+    }));
+    let mut details = details.map(|v2| v2.v2).unwrap_or(BTreeMap::new());
+    Ok(CrateListingV2 {
+        v2: bins.into_iter().map(|(id, bins)| {
+            let krate = details.remove(&id).unwrap_or_else(|| {
+                InstalledCrate {
+                    bins: BTreeSet::new(),
+                    features: Vec::new(),
+                    no_default_features: false,
+                    profile: "release".to_string(),
+                    target: None,
+                    rustc: None,
+                }
+            });
+            (id, InstalledCrate { bins: bins, ..krate })
+        }).collect(),
     })
 }
 
-fn write_crate_list(path: &Path, listing: CrateListingV1) -> CargoResult<()> {
-    let metadata = path.join(".crates.toml");
+fn read_toml<T: Decodable>(path: &Path) -> CargoResult<Option<T>> {
+    let mut f = match File::open(path) {
+        Ok(f) => f,
+        Err(..) => return Ok(None),
+    };
+    let mut contents = String::new();
+    try!(f.read_to_string(&mut contents));
+    let value = try!(toml::decode_str(&contents).chain_error(|| {
+        internal("invalid TOML found for metadata")
+    }));
+    Ok(Some(value))
+}
+
+fn write_crate_list(path: &Path, listing: CrateListingV2) -> CargoResult<()> {
+    let v1 = CrateListingV1 {
+        v1: listing.v2.iter().map(|(id, krate)| {
+            (id.clone(), krate.bins.clone())
+        }).collect(),
+    };
+    // The details are written first, so that the listing never names a
+    // package whose details are out of date.
+    try!(write_toml(&path.join(".crates2.toml"), &listing));
+    write_toml(&path.join(".crates.toml"), &CrateListing::V1(v1))
+}
+
+fn write_toml<T: Encodable>(path: &Path, value: &T) -> CargoResult<()> {
     (|| -> CargoResult<_> {
-        let mut f = try!(File::create(&metadata));
-        let data = toml::encode_str::<CrateListing>(&CrateListing::V1(listing));
-        try!(f.write_all(data.as_bytes()));
+        // Written to a temporary file first so that the file is replaced in
+        // one step and never left half written.
+        let mut tmp = path.as_os_str().to_os_string();
+        tmp.push(".tmp");
+        let data = toml::encode_str(value);
+        try!(File::create(&tmp).and_then(|mut f| f.write_all(data.as_bytes())));
+        try!(fs::rename(&tmp, path));
         Ok(())
     }).chain_error(|| {
         human(format!("failed to write crate metadata at `{}`",
-                      metadata.display()))
+                      path.display()))
     })
 }
 
-pub fn install_list(dst: Option<&str>, config: &Config) -> CargoResult<()> {
+pub fn install_list(dst: Option<&str>, outdated: bool, config: &Config)
+                    -> CargoResult<()> {
     let dst = try!(resolve_root(dst, config));
     let list = try!(read_crate_list(&dst));
+    if outdated {
+        return list_outdated(&list, config)
+    }
     let mut shell = config.shell();
     let out = shell.out();
-    for (k, v) in list.v1.iter() {
+    for (k, v) in list.v2.iter() {
         try!(writeln!(out, "{}:", k));
-        for bin in v {
+        for bin in v.bins.iter() {
             try!(writeln!(out, "    {}", bin));
         }
     }
     Ok(())
 }
 
+// Print the installed packages for which a newer version has been published
+// to their registry. Packages installed from git or a path are skipped.
+fn list_outdated(list: &CrateListingV2, config: &Config) -> CargoResult<()> {
+    let mut by_source = HashMap::new();
+    for id in list.v2.keys().filter(|id| id.source_id().is_registry()) {
+        by_source.entry(id.source_id().with_precise(None))
+                 .or_insert(Vec::new())
+                 .push(id);
+    }
+
+    let mut newer = Vec::new();
+    for (source_id, ids) in by_source.into_iter() {
+        let mut source = RegistrySource::new(&source_id, config);
+        try!(source.update());
+        for id in ids {
+            let latest = try!(source.summaries(id.name())).iter()
+                                .filter(|&&(_, yanked)| !yanked)
+                                .map(|s| s.0.version().clone())
+                                .max();
+            match latest {
+                Some(ref v) if v > id.version() => newer.push((id, v.clone())),
+                _ => {}
+            }
+        }
+    }
+
+    newer.sort();
+    if newer.is_empty() {
+        try!(config.shell().say("All installed packages are up to date",
+                                BLACK));
+    }
+    for (id, version) in newer {
+        try!(config.shell().say(format!("{} -> v{}", id, version), BLACK));
+    }
+    Ok(())
+}
+
 pub fn uninstall(root: Option<&str>,
                  spec: &str,
                  bins: &[String],
@@ -273,14 +589,14 @@ pub fn uninstall(root: Option<&str>,
     let mut metadata = try!(read_crate_list(&root));
     let mut to_remove = Vec::new();
     {
-        let result = try!(PackageIdSpec::query_str(spec, metadata.v1.keys()))
+        let result = try!(PackageIdSpec::query_str(spec, metadata.v2.keys()))
                                         .clone();
-        let mut installed = match metadata.v1.entry(result.clone()) {
+        let mut installed = match metadata.v2.entry(result.clone()) {
             Entry::Occupied(e) => e,
             Entry::Vacant(..) => panic!("entry not found: {}", result),
         };
         let dst = root.join("bin");
-        for bin in installed.get() {
+        for bin in installed.get().bins.iter() {
             let bin = dst.join(bin);
             if fs::metadata(&bin).is_err() {
                 return Err(human(format!("corrupt metadata, `{}` does not \
@@ -298,22 +614,22 @@ pub fn uninstall(root: Option<&str>,
         }).collect::<Vec<_>>();
 
         for bin in bins.iter() {
-            if !installed.get().contains(bin) {
+            if !installed.get().bins.contains(bin) {
                 return Err(human(format!("binary `{}` not installed as part \
                                           of `{}`", bin, result)))
             }
         }
 
         if bins.len() == 0 {
-            to_remove.extend(installed.get().iter().map(|b| dst.join(b)));
-            installed.get_mut().clear();
+            to_remove.extend(installed.get().bins.iter().map(|b| dst.join(b)));
+            installed.get_mut().bins.clear();
         } else {
             for bin in bins.iter() {
                 to_remove.push(dst.join(bin));
-                installed.get_mut().remove(bin);
+                installed.get_mut().bins.remove(bin);
             }
         }
-        if installed.get().len() == 0 {
+        if installed.get().bins.len() == 0 {
             installed.remove();
         }
     }
@@ -326,6 +642,29 @@ pub fn uninstall(root: Option<&str>,
     Ok(())
 }
 
+// All installs build in the same target directory, which is kept afterwards
+// so that dependencies shared by several tools are only compiled once and an
+// interrupted build can be resumed. It lives in the Cargo home directory
+// unless `install.target-dir` is configured.
+/// The directory `cargo install` builds crates in, which is shared by all
+/// installations so that common dependencies are only compiled once.
+/// `cargo cache gc` removes its contents when it hasn't been used for a
+/// while.
+pub fn install_target_dir(config: &Config) -> CargoResult<PathBuf> {
+    match try!(config.get_string("install.target-dir")) {
+        Some((dir, definition)) => {
+            // Relative paths are relative to the directory containing the
+            // `.cargo` directory the value was defined in, even without a
+            // `/` in them.
+            let mut path = definition;
+            path.pop();
+            path.pop();
+            Ok(path.join(dir))
+        }
+        None => Ok(config.home().join("target-install")),
+    }
+}
+
 fn resolve_root(flag: Option<&str>, config: &Config) -> CargoResult<PathBuf> {
     let config_root = try!(config.get_string("install.root"));
     Ok(flag.map(PathBuf::from).or_else(|| {
@@ -1,16 +1,19 @@
 use std::env;
-use std::fs;
+use std::fs::{self, File};
 use std::io::prelude::*;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use rustc_serialize::{Decodable, Decoder};
 
 use git2::Config as GitConfig;
 
 use term::color::BLACK;
+use time;
 
+use core::{GitReference, SourceId};
+use sources::GitSource;
 use util::{GitRepo, HgRepo, CargoResult, human, ChainError, internal};
-use util::{Config, paths};
+use util::{self, Config, ToUrl, paths};
 
 use toml;
 
@@ -22,6 +25,7 @@ pub struct NewOptions<'a> {
     pub bin: bool,
     pub path: &'a str,
     pub name: Option<&'a str>,
+    pub template: Option<&'a str>,
 }
 
 impl Decodable for VersionControl {
@@ -42,6 +46,14 @@ struct CargoNewConfig {
     name: Option<String>,
     email: Option<String>,
     version_control: Option<VersionControl>,
+    /// The template along with the directory relative paths are relative to.
+    template: Option<(String, PathBuf)>,
+}
+
+struct SourceFileInformation {
+    relative_path: String,
+    target_name: String,
+    bin: bool,
 }
 
 pub fn new(opts: NewOptions, config: &Config) -> CargoResult<()> {
@@ -50,37 +62,104 @@ pub fn new(opts: NewOptions, config: &Config) -> CargoResult<()> {
         return Err(human(format!("Destination `{}` already exists",
                                  path.display())))
     }
-    let name = match opts.name {
-        Some(name) => name,
-        None => {
-            let dir_name = try!(path.file_name().and_then(|s| s.to_str()).chain_error(|| {
-                human(&format!("cannot create a project with a non-unicode name: {:?}",
-                               path.file_name().unwrap()))
-            }));
-            if opts.bin {
-                dir_name
-            } else {
-                let new_name = strip_rust_affixes(dir_name);
-                if new_name != dir_name {
-                    let message = format!(
-                        "note: package will be named `{}`; use --name to override",
-                        new_name);
-                    try!(config.shell().say(&message, BLACK));
-                }
-                new_name
-            }
-        }
-    };
+    let name = try!(get_name(&path, &opts, config));
+    try!(check_name(name));
+
+    let existing_vcs = existing_vcs_repo(path.parent().unwrap());
+    mk(config, &path, name, &opts, &[], existing_vcs, false).chain_error(|| {
+        human(format!("Failed to create project `{}` at `{}`",
+                      name, path.display()))
+    })
+}
+
+pub fn init(opts: NewOptions, config: &Config) -> CargoResult<()> {
+    // Normalized so that `file_name` of paths such as `.` is the name of the
+    // directory itself.
+    let path = paths::normalize_path(&config.cwd().join(opts.path));
+    if !fs::metadata(&path).map(|m| m.is_dir()).unwrap_or(false) {
+        return Err(human(format!("cannot initialize `{}` as a project, it is \
+                                  not an existing directory", path.display())))
+    }
+    if fs::metadata(&path.join("Cargo.toml")).is_ok() {
+        return Err(human("`cargo init` cannot be run on existing Cargo \
+                          projects"))
+    }
+    let name = try!(get_name(&path, &opts, config));
+    try!(check_name(name));
+
+    let sources = detect_source_paths(&path, name);
+
+    let existing_vcs = existing_vcs_repo(&path);
+    mk(config, &path, name, &opts, &sources, existing_vcs, true).chain_error(|| {
+        human(format!("Failed to create project `{}` at `{}`",
+                      name, path.display()))
+    })
+}
+
+fn get_name<'a>(path: &'a Path, opts: &'a NewOptions,
+                config: &Config) -> CargoResult<&'a str> {
+    if let Some(name) = opts.name {
+        return Ok(name)
+    }
+    let dir_name = try!(path.file_name().and_then(|s| s.to_str()).chain_error(|| {
+        human(&format!("cannot create a project with a non-unicode name: {:?}",
+                       path.file_name().unwrap()))
+    }));
+    if opts.bin {
+        return Ok(dir_name)
+    }
+    let new_name = strip_rust_affixes(dir_name);
+    if new_name != dir_name {
+        let message = format!(
+            "note: package will be named `{}`; use --name to override",
+            new_name);
+        try!(config.shell().say(&message, BLACK));
+    }
+    Ok(new_name)
+}
+
+fn check_name(name: &str) -> CargoResult<()> {
     for c in name.chars() {
         if c.is_alphanumeric() { continue }
         if c == '_' || c == '-' { continue }
         return Err(human(&format!("Invalid character `{}` in crate name: `{}`",
                                   c, name)));
     }
-    mk(config, &path, name, &opts).chain_error(|| {
-        human(format!("Failed to create project `{}` at `{}`",
-                      name, path.display()))
-    })
+    Ok(())
+}
+
+// Find the source files of a project which is being adopted by `cargo init`.
+// Files named `<name>.rs` are a binary if they define a `main` function and a
+// library otherwise.
+fn detect_source_paths(path: &Path, name: &str) -> Vec<SourceFileInformation> {
+    let name_rs = format!("{}.rs", name);
+    let candidates = [
+        ("src/main.rs", Some(true)),
+        ("main.rs", Some(true)),
+        ("src/lib.rs", Some(false)),
+        ("lib.rs", Some(false)),
+        (&name_rs[..], None),
+    ];
+
+    let mut sources = Vec::new();
+    for &(file, bin) in candidates.iter() {
+        let contents = match paths::read(&path.join(file)) {
+            Ok(contents) => contents,
+            Err(..) => continue,
+        };
+        let bin = bin.unwrap_or_else(|| contents.contains("fn main"));
+        // Only one library is allowed per package, and a second binary with
+        // the same name would clash with the first.
+        if sources.iter().any(|s: &SourceFileInformation| s.bin == bin) {
+            continue
+        }
+        sources.push(SourceFileInformation {
+            relative_path: file.to_string(),
+            target_name: name.to_string(),
+            bin: bin,
+        });
+    }
+    sources
 }
 
 fn strip_rust_affixes(name: &str) -> &str {
@@ -97,37 +176,103 @@ fn strip_rust_affixes(name: &str) -> &str {
     name
 }
 
-fn existing_vcs_repo(path: &Path) -> bool {
-    GitRepo::discover(path).is_ok() || HgRepo::discover(path).is_ok()
+// The version control system of the repository which `path` is in, if any.
+fn existing_vcs_repo(path: &Path) -> Option<VersionControl> {
+    if GitRepo::discover(path).is_ok() {
+        Some(VersionControl::Git)
+    } else if HgRepo::discover(path).is_ok() {
+        Some(VersionControl::Hg)
+    } else {
+        None
+    }
 }
 
-fn mk(config: &Config, path: &Path, name: &str,
-      opts: &NewOptions) -> CargoResult<()> {
-    let cfg = try!(global_config(config));
-    let mut ignore = "target\n".to_string();
-    let in_existing_vcs_repo = existing_vcs_repo(path.parent().unwrap());
-    if !opts.bin {
-        ignore.push_str("Cargo.lock\n");
+// Add the lines of `ignore` which aren't already present to the ignore file
+// at `path`, creating it if needed.
+fn write_ignore_file(path: &Path, ignore: &str) -> CargoResult<()> {
+    let mut contents = paths::read(path).unwrap_or(String::new());
+    let missing = ignore.lines().filter(|line| {
+        !contents.lines().any(|l| l.trim() == *line)
+    }).map(|s| s.to_string()).collect::<Vec<_>>();
+    if missing.is_empty() {
+        return Ok(())
+    }
+    if !contents.is_empty() && !contents.ends_with("\n") {
+        contents.push_str("\n");
+    }
+    for line in missing {
+        contents.push_str(&line);
+        contents.push_str("\n");
+    }
+    paths::write(path, contents.as_bytes())
+}
+
+// Escape the characters of `s` which are special in the regular expressions
+// of `.hgignore`, where `#` starts a comment as well.
+fn hg_escape(s: &str) -> String {
+    let mut escaped = String::new();
+    for c in s.chars() {
+        if "\\.+*?()|[]{}^$#".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// Write `contents` to `path` unless the file already exists.
+fn write_new_file(path: &Path, contents: &[u8]) -> CargoResult<()> {
+    if fs::metadata(path).is_ok() {
+        return Ok(())
     }
+    try!(fs::create_dir_all(path.parent().unwrap()));
+    paths::write(path, contents)
+}
 
-    let vcs = match (opts.version_control, cfg.version_control, in_existing_vcs_repo) {
-        (None, None, false) => VersionControl::Git,
-        (None, Some(option), false) => option,
+fn mk(config: &Config, path: &Path, name: &str, opts: &NewOptions,
+      sources: &[SourceFileInformation],
+      existing_vcs: Option<VersionControl>,
+      adopt_existing_vcs: bool) -> CargoResult<()> {
+    let cfg = try!(global_config(config));
+
+    // Unless asked otherwise, a project which `cargo init` creates inside an
+    // existing repository becomes part of it, while one which `cargo new`
+    // creates inside a repository isn't put under version control at all.
+    let vcs = match (opts.version_control, cfg.version_control, existing_vcs) {
+        (None, None, None) => VersionControl::Git,
+        (None, Some(option), None) => option,
         (Some(option), _, _) => option,
-        (_, _, true) => VersionControl::NoVcs,
+        (None, _, Some(existing)) if adopt_existing_vcs => existing,
+        (None, _, Some(_)) => VersionControl::NoVcs,
+    };
+    let in_existing_repo = adopt_existing_vcs && opts.version_control.is_none() &&
+                           existing_vcs.is_some();
+
+    // Fetch the template before creating anything, so a bad template doesn't
+    // leave a half created project behind.
+    let template = match (opts.template, &cfg.template) {
+        (Some(template), _) => {
+            Some(try!(template_dir(template, config.cwd(), config)))
+        }
+        (None, &Some((ref template, ref base))) => {
+            Some(try!(template_dir(template, base, config)))
+        }
+        (None, &None) => None,
     };
 
     match vcs {
         VersionControl::Git => {
-            try!(GitRepo::init(path));
-            try!(paths::write(&path.join(".gitignore"), ignore.as_bytes()));
+            if !in_existing_repo && fs::metadata(&path.join(".git")).is_err() {
+                try!(GitRepo::init(path));
+            }
         },
         VersionControl::Hg => {
-            try!(HgRepo::init(path));
-            try!(paths::write(&path.join(".hgignore"), ignore.as_bytes()));
+            if !in_existing_repo && fs::metadata(&path.join(".hg")).is_err() {
+                try!(HgRepo::init(path));
+            }
         },
         VersionControl::NoVcs => {
-            try!(fs::create_dir(path));
+            try!(fs::create_dir_all(path));
         },
     };
 
@@ -142,23 +287,95 @@ fn mk(config: &Config, path: &Path, name: &str,
         (None, None, name, None) => name,
     };
 
-    try!(paths::write(&path.join("Cargo.toml"), format!(
+    if let Some(ref template) = template {
+        let year = (time::now().tm_year + 1900).to_string();
+        try!(copy_template(template, path, &[
+            ("{{name}}", name),
+            ("{{authors}}", &author[..]),
+            ("{{year}}", &year[..]),
+        ]).chain_error(|| {
+            human(format!("failed to copy the template at `{}`",
+                          template.display()))
+        }));
+    }
+
+    let bin = opts.bin || sources.iter().any(|s| s.bin) ||
+              fs::metadata(&path.join("src/main.rs")).is_ok();
+    let mut ignore = "target\n".to_string();
+    if !bin {
+        ignore.push_str("Cargo.lock\n");
+    }
+    match vcs {
+        VersionControl::Git => {
+            try!(write_ignore_file(&path.join(".gitignore"), &ignore));
+        }
+        VersionControl::Hg => {
+            // Mercurial only reads the ignore file at the root of the
+            // repository, so the patterns are anchored to the directory of the
+            // project within it to leave other projects' files alone.
+            let root = if in_existing_repo {
+                try!(HgRepo::root(path))
+            } else {
+                path.to_path_buf()
+            };
+            let mut prefix = "^".to_string();
+            for part in util::without_prefix(path, &root).unwrap().components() {
+                prefix.push_str(&hg_escape(&part.as_os_str().to_string_lossy()));
+                prefix.push_str("/");
+            }
+            let mut patterns = format!("{}target/\n", prefix);
+            if !bin {
+                patterns.push_str(&format!("{}Cargo\\.lock$\n", prefix));
+            }
+            try!(write_ignore_file(&root.join(".hgignore"), &patterns));
+        }
+        VersionControl::NoVcs => {}
+    }
+
+    // Sources in the default locations are found by Cargo on its own, the
+    // others need to be listed explicitly.
+    let mut targets = String::new();
+    for source in sources.iter() {
+        match (source.bin, &source.relative_path[..]) {
+            (true, "src/main.rs") | (false, "src/lib.rs") => {}
+            (true, file) => {
+                targets.push_str(&format!(r#"
+[[bin]]
+name = "{}"
+path = {}
+"#, source.target_name, toml::Value::String(file.to_string())));
+            }
+            (false, file) => {
+                targets.push_str(&format!(r#"
+[lib]
+path = {}
+"#, toml::Value::String(file.to_string())));
+            }
+        }
+    }
+
+    // A template may bring its own manifest and sources, which are left as
+    // they are.
+    try!(write_new_file(&path.join("Cargo.toml"), format!(
 r#"[package]
 name = "{}"
 version = "0.1.0"
 authors = [{}]
-"#, name, toml::Value::String(author)).as_bytes()));
+{}"#, name, toml::Value::String(author), targets).as_bytes()));
 
-    try!(fs::create_dir(&path.join("src")));
+    if !sources.is_empty() || fs::metadata(&path.join("src/main.rs")).is_ok() ||
+       fs::metadata(&path.join("src/lib.rs")).is_ok() {
+        return Ok(())
+    }
 
     if opts.bin {
-        try!(paths::write(&path.join("src/main.rs"), b"\
+        try!(write_new_file(&path.join("src/main.rs"), b"\
 fn main() {
     println!(\"Hello, world!\");
 }
 "));
     } else {
-        try!(paths::write(&path.join("src/lib.rs"), b"\
+        try!(write_new_file(&path.join("src/lib.rs"), b"\
 #[test]
 fn it_works() {
 }
@@ -168,6 +385,75 @@ fn it_works() {
     Ok(())
 }
 
+// Templates are either a directory or the URL of a git repository, which is
+// fetched like any other git source. Relative paths are relative to `base`.
+// The `master` branch of a repository is used unless the URL names another
+// one as its fragment, as in `https://example.com/template#branch`.
+fn template_dir(template: &str, base: &Path,
+                config: &Config) -> CargoResult<PathBuf> {
+    if !template.contains("://") {
+        let dir = base.join(template);
+        if !fs::metadata(&dir).map(|m| m.is_dir()).unwrap_or(false) {
+            return Err(human(format!("the template `{}` is not a directory",
+                                     dir.display())))
+        }
+        return Ok(dir)
+    }
+    let mut url = try!(template.to_url().map_err(human));
+    let branch = url.fragment.take().unwrap_or("master".to_string());
+    let source_id = SourceId::for_git(&url, GitReference::Branch(branch));
+    let mut source = GitSource::new(&source_id, config);
+    let dir = try!(source.checkout().chain_error(|| {
+        human(format!("failed to fetch the template `{}`", template))
+    }));
+    Ok(dir.to_path_buf())
+}
+
+// Copy a template into the new project, replacing the placeholders in all of
+// its text files. Files which already exist in the project are kept.
+//
+// The placeholders of a `Cargo.toml` are expected to be within strings, so
+// their values are escaped as TOML requires, while they're inserted as they
+// are everywhere else.
+fn copy_template(src: &Path, dst: &Path,
+                 placeholders: &[(&str, &str)]) -> CargoResult<()> {
+    for entry in try!(fs::read_dir(src)) {
+        let entry = try!(entry);
+        let file_name = entry.file_name();
+        if file_name.to_str() == Some(".git") || file_name.to_str() == Some(".hg") {
+            continue
+        }
+        let (src, dst) = (entry.path(), dst.join(&file_name));
+        if try!(fs::metadata(&src)).is_dir() {
+            try!(fs::create_dir_all(&dst));
+            try!(copy_template(&src, &dst, placeholders));
+            continue
+        }
+        let mut contents = Vec::new();
+        try!(try!(File::open(&src)).read_to_end(&mut contents));
+        let is_manifest = file_name.to_str() == Some("Cargo.toml");
+        let contents = match String::from_utf8(contents) {
+            Ok(mut text) => {
+                for &(placeholder, value) in placeholders.iter() {
+                    let value = if is_manifest {
+                        // Strip the quotes around the escaped string.
+                        let quoted = toml::Value::String(value.to_string())
+                                                .to_string();
+                        quoted[1..quoted.len() - 1].to_string()
+                    } else {
+                        value.to_string()
+                    };
+                    text = text.replace(placeholder, &value);
+                }
+                text.into_bytes()
+            }
+            Err(e) => e.into_bytes(),
+        };
+        try!(write_new_file(&dst, &contents));
+    }
+    Ok(())
+}
+
 fn discover_author() -> CargoResult<(String, Option<String>)> {
     let git_config = GitConfig::open_default().ok();
     let git_config = git_config.as_ref();
@@ -208,10 +494,19 @@ fn global_config(config: &Config) -> CargoResult<CargoNewConfig> {
         }
         None => None
     };
+    // Like other paths in configuration, a relative template path is relative
+    // to the directory containing the `.cargo` directory it was defined in.
+    let template = try!(config.get_string("cargo-new.template")).map(|(t, p)| {
+        let mut base = p;
+        base.pop();
+        base.pop();
+        (t, base)
+    });
     Ok(CargoNewConfig {
         name: name,
         email: email,
         version_control: vcs,
+        template: template,
     })
 }
 
@@ -0,0 +1,145 @@
+use std::cmp;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use semver::Version;
+use term::color::BLACK;
+
+use core::registry::PackageRegistry;
+use core::{Package, PackageId, Source, SourceId};
+use ops;
+use sources::RegistrySource;
+use util::{CargoResult, Config, ChainError, internal};
+
+/// A single entry in the report generated by `cargo outdated`.
+#[derive(RustcEncodable, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct OutdatedPackage {
+    pub name: String,
+    pub source: String,
+    /// The version currently recorded in the lockfile.
+    pub current: String,
+    /// The newest non-yanked version which is semver compatible with
+    /// `current`, if there is one newer than `current`.
+    pub compatible: Option<String>,
+    /// The newest non-yanked version published at all, if there is one
+    /// newer than `current`.
+    pub latest: Option<String>,
+    /// Whether the root package depends on this package directly.
+    pub direct: bool,
+}
+
+/// Compare each locked registry package of the package at `manifest_path`
+/// against the versions currently available in its registry.
+///
+/// Only packages for which a newer (non-yanked) version exists are returned,
+/// sorted by name.
+pub fn outdated(manifest_path: &Path, config: &Config)
+                -> CargoResult<Vec<OutdatedPackage>> {
+    let package = try!(Package::for_path(manifest_path, config));
+    let mut registry = PackageRegistry::new(config);
+    let resolve = try!(ops::resolve_pkg(&mut registry, &package));
+
+    let direct = resolve.deps(resolve.root()).into_iter().flat_map(|i| i)
+                        .collect::<HashSet<_>>();
+
+    // Group all locked registry packages by the registry they came from so
+    // each index only needs to be updated once.
+    let mut by_source = HashMap::new();
+    for pkg in resolve.iter() {
+        if pkg == resolve.root() || !pkg.source_id().is_registry() {
+            continue
+        }
+        by_source.entry(pkg.source_id().with_precise(None))
+                 .or_insert(Vec::new())
+                 .push(pkg);
+    }
+
+    let mut ret = Vec::new();
+    for (source_id, pkgs) in by_source.into_iter() {
+        let mut source = RegistrySource::new(&source_id, config);
+        try!(source.update().chain_error(|| {
+            internal(format!("failed to update {}", source_id))
+        }));
+        for pkg in pkgs {
+            let versions = try!(source.summaries(pkg.name())).iter()
+                                  .filter(|&&(_, yanked)| !yanked)
+                                  .map(|s| s.0.version().clone())
+                                  .collect::<Vec<_>>();
+            let entry = compare_versions(pkg, &versions, direct.contains(&pkg),
+                                         &source_id);
+            if entry.compatible.is_some() || entry.latest.is_some() {
+                ret.push(entry);
+            }
+        }
+    }
+    ret.sort();
+    Ok(ret)
+}
+
+fn compare_versions(pkg: &PackageId, versions: &[Version], direct: bool,
+                    source_id: &SourceId) -> OutdatedPackage {
+    let current = pkg.version();
+    let newer = |matches: &Fn(&Version) -> bool| {
+        versions.iter().filter(|v| *v > current && matches(*v))
+                .max().map(|v| v.to_string())
+    };
+    OutdatedPackage {
+        name: pkg.name().to_string(),
+        source: source_id.to_url(),
+        current: current.to_string(),
+        compatible: newer(&|v| is_compatible(current, v)),
+        latest: newer(&|_| true),
+        direct: direct,
+    }
+}
+
+// Whether `v` would match the requirement `^current`. This is decided from
+// the versions themselves, as not every version (such as one with build
+// metadata) can be turned into a requirement. As with requirements, a
+// pre-release only matches if it's a pre-release of the current version.
+fn is_compatible(current: &Version, v: &Version) -> bool {
+    if !v.pre.is_empty() &&
+       (v.major, v.minor, v.patch) != (current.major, current.minor, current.patch) {
+        return false
+    }
+    if current.major != 0 {
+        v.major == current.major
+    } else if current.minor != 0 {
+        v.major == 0 && v.minor == current.minor
+    } else {
+        v.major == 0 && v.minor == 0 && v.patch == current.patch
+    }
+}
+
+/// Print a report generated by `outdated` as a human readable table.
+pub fn print_outdated(report: &[OutdatedPackage], config: &Config)
+                      -> CargoResult<()> {
+    if report.is_empty() {
+        try!(config.shell().say("All dependencies are up to date", BLACK));
+        return Ok(())
+    }
+
+    let header = ["Name".to_string(), "Current".to_string(),
+                  "Compatible".to_string(), "Latest".to_string()];
+    let rows = report.iter().map(|p| {
+        let kind = if p.direct {""} else {" (transitive)"};
+        [format!("{}{}", p.name, kind),
+         p.current.clone(),
+         p.compatible.clone().unwrap_or("-".to_string()),
+         p.latest.clone().unwrap_or("-".to_string())]
+    }).collect::<Vec<_>>();
+
+    let mut widths = [0; 3];
+    for row in Some(&header).into_iter().chain(rows.iter()) {
+        for (width, col) in widths.iter_mut().zip(row.iter()) {
+            *width = cmp::max(*width, col.len());
+        }
+    }
+    for row in Some(&header).into_iter().chain(rows.iter()) {
+        let line = format!("{:<w0$}   {:<w1$}   {:<w2$}   {}",
+                           row[0], row[1], row[2], row[3],
+                           w0 = widths[0], w1 = widths[1], w2 = widths[2]);
+        try!(config.shell().say(line, BLACK));
+    }
+    Ok(())
+}
@@ -1,16 +1,21 @@
+use std::env;
 use std::io::prelude::*;
+use std::io::Cursor;
 use std::fs::{self, File};
-use std::path::{self, Path, PathBuf};
+use std::path::{Path, PathBuf};
 
+use filetime::FileTime;
+use git2;
+use rustc_serialize::json;
 use semver::VersionReq;
-use tar::Archive;
+use tar::{Archive, Header};
 use flate2::{GzBuilder, Compression};
 use flate2::read::GzDecoder;
 
-use core::{SourceId, Package, PackageId};
+use core::Package;
 use core::dependency::Kind;
 use sources::PathSource;
-use util::{self, CargoResult, human, internal, ChainError, Config};
+use util::{self, CargoResult, human, internal, ChainError, Config, Sha256};
 use ops;
 
 struct Bomb { path: Option<PathBuf> }
@@ -24,26 +29,54 @@ impl Drop for Bomb {
     }
 }
 
+pub struct PackageOpts<'cfg> {
+    pub config: &'cfg Config,
+    /// Only print the files which would be packaged.
+    pub list: bool,
+    /// Warn about missing human-usable metadata.
+    pub check_metadata: bool,
+    /// Package even if the git working tree has uncommitted changes.
+    pub allow_dirty: bool,
+    /// Build the unpacked tarball to ensure it is complete.
+    pub verify: bool,
+}
+
+/// Information about the version control state of a package, recorded as
+/// `.cargo_vcs_info.json` in its tarball.
+#[derive(RustcEncodable)]
+struct VcsInfo {
+    git: GitVcsInfo,
+}
+
+#[derive(RustcEncodable)]
+struct GitVcsInfo {
+    sha1: String,
+}
+
 pub fn package(manifest_path: &Path,
-               config: &Config,
-               verify: bool,
-               list: bool,
-               metadata: bool) -> CargoResult<Option<PathBuf>> {
+               opts: &PackageOpts) -> CargoResult<Option<PathBuf>> {
+    let config = opts.config;
     let mut src = try!(PathSource::for_path(manifest_path.parent().unwrap(),
                                             config));
     let pkg = try!(src.root_package());
 
-    if metadata {
+    if opts.check_metadata {
         try!(check_metadata(&pkg, config));
     }
 
     try!(check_dependencies(&pkg, config));
 
-    if list {
+    if opts.list {
         let root = pkg.root();
         let mut list: Vec<_> = try!(src.list_files(&pkg)).iter().map(|file| {
             util::without_prefix(&file, &root).unwrap().to_path_buf()
         }).collect();
+        if list.iter().any(|p| p == Path::new("Cargo.toml")) {
+            list.push(PathBuf::from("Cargo.toml.orig"));
+        }
+        if include_lockfile(&pkg) {
+            list.push(PathBuf::from("Cargo.lock"));
+        }
         list.sort();
         for file in list.iter() {
             println!("{}", file.display());
@@ -51,18 +84,26 @@ pub fn package(manifest_path: &Path,
         return Ok(None)
     }
 
+    let vcs_info = try!(check_repo_state(&pkg, &src, opts.allow_dirty));
+
     let filename = format!("package/{}-{}.crate", pkg.name(), pkg.version());
     let target_dir = config.target_dir(&pkg);
     let dst = target_dir.join(&filename);
-    if fs::metadata(&dst).is_ok() { return Ok(Some(dst)) }
+    let files = try!(package_files(&pkg, &src, &dst));
+    if try!(is_up_to_date(&pkg, &files, vcs_info.as_ref(), &dst)) {
+        return Ok(Some(dst))
+    }
+    if fs::metadata(&dst).is_ok() {
+        try!(fs::remove_file(&dst));
+    }
 
     let mut bomb = Bomb { path: Some(dst.clone()) };
 
     try!(config.shell().status("Packaging", pkg.package_id().to_string()));
-    try!(tar(&pkg, &src, config, &dst).chain_error(|| {
+    try!(tar(&pkg, &files, vcs_info.as_ref(), config, &dst).chain_error(|| {
         human("failed to prepare local package for uploading")
     }));
-    if verify {
+    if opts.verify {
         try!(run_verify(config, &pkg, &dst).chain_error(|| {
             human("failed to verify package tarball")
         }))
@@ -70,6 +111,65 @@ pub fn package(manifest_path: &Path,
     Ok(Some(bomb.path.take().unwrap()))
 }
 
+// Check that none of the files about to be packaged have uncommitted changes
+// in git, and return the commit they were packaged from.
+//
+// As with `PathSource::list_files`, the package is only considered to be
+// under version control if it is at the root of a git repository. If the
+// working tree is dirty but `allow_dirty` is set, no commit is recorded as
+// it would not describe the packaged contents.
+#[allow(deprecated)] // connect => join in 1.3
+fn check_repo_state(pkg: &Package, src: &PathSource, allow_dirty: bool)
+                    -> CargoResult<Option<VcsInfo>> {
+    let repo = match git2::Repository::open(pkg.root()) {
+        Ok(repo) => repo,
+        Err(..) => return Ok(None),
+    };
+    let workdir = match repo.workdir() {
+        Some(workdir) => workdir.to_path_buf(),
+        None => return Ok(None),
+    };
+    let files = try!(src.list_files(pkg));
+
+    let mut opts = git2::StatusOptions::new();
+    opts.include_untracked(true).recurse_untracked_dirs(true);
+    let statuses = try!(repo.statuses(Some(&mut opts)));
+    let mut dirty = statuses.iter().filter(|entry| {
+        entry.status() != git2::STATUS_CURRENT &&
+            entry.status() != git2::STATUS_IGNORED
+    }).filter_map(|entry| {
+        entry.path().map(|p| workdir.join(p))
+    }).filter(|path| files.contains(path)).map(|path| {
+        util::without_prefix(&path, &workdir).unwrap().display().to_string()
+    }).collect::<Vec<_>>();
+
+    if !dirty.is_empty() {
+        if allow_dirty {
+            return Ok(None)
+        }
+        dirty.sort();
+        return Err(human(format!("{} files in the working directory contain \
+                                  changes that were not yet committed into \
+                                  git:\n\n{}\n\nto proceed despite this, \
+                                  pass the `--allow-dirty` flag",
+                                 dirty.len(), dirty.connect("\n"))))
+    }
+
+    // A freshly initialized repository has no commit to point at.
+    let head = match repo.head().ok().and_then(|h| h.target()) {
+        Some(oid) => oid,
+        None => return Ok(None),
+    };
+    Ok(Some(VcsInfo { git: GitVcsInfo { sha1: head.to_string() } }))
+}
+
+// Packages with binaries ship their lockfile so that `cargo install --locked`
+// can build them with exactly the dependencies they were tested with.
+fn include_lockfile(pkg: &Package) -> bool {
+    pkg.targets().iter().any(|t| t.is_bin()) &&
+        fs::metadata(pkg.root().join("Cargo.lock")).is_ok()
+}
+
 // check that the package has some piece of metadata that a human can
 // use to tell what the package is about.
 #[allow(deprecated)] // connect => join in 1.3
@@ -132,8 +232,87 @@ fn check_dependencies(pkg: &Package, config: &Config) -> CargoResult<()> {
     Ok(())
 }
 
-fn tar(pkg: &Package, src: &PathSource, config: &Config,
-       dst: &Path) -> CargoResult<()> {
+// The files making up the package as paths relative to its root, using `/`
+// as the separator, along with where they are. They are sorted by path.
+#[allow(deprecated)] // connect => join in 1.3
+fn package_files(pkg: &Package, src: &PathSource, dst: &Path)
+                 -> CargoResult<Vec<(String, PathBuf)>> {
+    let root = pkg.root();
+    let mut files = Vec::new();
+    for file in try!(src.list_files(pkg)).into_iter() {
+        if &*file == dst { continue }
+        let relative = {
+            let relative = util::without_prefix(&file, &root).unwrap();
+            let parts = relative.components().map(|c| {
+                c.as_os_str().to_str()
+            }).collect::<Option<Vec<_>>>();
+            try!(parts.chain_error(|| {
+                human(format!("non-utf8 path in source directory: {}",
+                              relative.display()))
+            })).connect("/")
+        };
+        files.push((relative, file));
+    }
+    if include_lockfile(pkg) {
+        files.push(("Cargo.lock".to_string(), root.join("Cargo.lock")));
+    }
+    files.sort();
+    Ok(files)
+}
+
+// A tarball left over from an earlier run is only reused if it holds exactly
+// the `files` which would be packaged now, none of which were modified after
+// it was made, and records the same commit.
+fn is_up_to_date(pkg: &Package, files: &[(String, PathBuf)],
+                 vcs_info: Option<&VcsInfo>, dst: &Path) -> CargoResult<bool> {
+    let mtime = match fs::metadata(dst) {
+        Ok(meta) => FileTime::from_last_modification_time(&meta),
+        Err(..) => return Ok(false),
+    };
+    for &(_, ref file) in files.iter() {
+        let meta = try!(fs::metadata(file));
+        if FileTime::from_last_modification_time(&meta) >= mtime {
+            return Ok(false)
+        }
+    }
+
+    let prefix = format!("{}-{}/", pkg.name(), pkg.version());
+    let vcs_path = format!("{}.cargo_vcs_info.json", prefix);
+    let mut expected = files.iter().map(|&(ref relative, _)| {
+        format!("{}{}", prefix, relative)
+    }).collect::<Vec<_>>();
+    if files.iter().any(|&(ref relative, _)| *relative == "Cargo.toml") {
+        expected.push(format!("{}Cargo.toml.orig", prefix));
+    }
+    if vcs_info.is_some() {
+        expected.push(vcs_path.clone());
+    }
+    expected.sort();
+
+    // Listing the entries of an archive requires seeking in it.
+    let mut contents = Vec::new();
+    try!(try!(GzDecoder::new(try!(File::open(dst)))).read_to_end(&mut contents));
+    let ar = Archive::new(Cursor::new(contents));
+    let mut entries = Vec::new();
+    for entry in try!(ar.files()) {
+        let mut entry = try!(entry);
+        let path = String::from_utf8_lossy(&entry.header().path_bytes())
+                          .into_owned();
+        if path == vcs_path {
+            let mut contents = String::new();
+            try!(entry.read_to_string(&mut contents));
+            if Some(contents) != vcs_info.map(|v| json::encode(v).unwrap()) {
+                return Ok(false)
+            }
+        }
+        entries.push(path);
+    }
+    entries.sort();
+    Ok(entries == expected)
+}
+
+fn tar(pkg: &Package, files: &[(String, PathBuf)], vcs_info: Option<&VcsInfo>,
+       config: &Config, dst: &Path) -> CargoResult<()> {
 
     if fs::metadata(&dst).is_ok() {
         return Err(human(format!("destination already exists: {}",
@@ -144,35 +323,112 @@ fn tar(pkg: &Package, src: &PathSource, config: &Config,
 
     let tmpfile = try!(File::create(dst));
 
-    // Prepare the encoder and its header
+    // Prepare the encoder and its header. The gzip header has its mtime
+    // pinned so the same sources always compress to the same bytes.
     let filename = Path::new(dst.file_name().unwrap());
     let encoder = GzBuilder::new().filename(try!(util::path2bytes(filename)))
+                                  .mtime(0)
                                   .write(tmpfile, Compression::Best);
 
-    // Put all package files into a compressed archive
+    // Put all package files into a compressed archive. The manifest is
+    // replaced with a normalized version, and the original is kept alongside
+    // it as `Cargo.toml.orig`.
+    //
+    // Entries are added in sorted order and their headers only record the
+    // path, size and whether the file is executable, so packaging the same
+    // sources twice yields an identical tarball.
     let ar = Archive::new(encoder);
     let root = pkg.root();
-    for file in try!(src.list_files(pkg)).iter() {
-        if &**file == dst { continue }
-        let relative = util::without_prefix(&file, &root).unwrap();
-        let relative = try!(relative.to_str().chain_error(|| {
-            human(format!("non-utf8 path in source directory: {}",
-                          relative.display()))
-        }));
-        let mut file = try!(File::open(file));
+    let mtime = try!(archive_mtime());
+    let packaged = files.iter().map(|&(_, ref file)| file.clone())
+                        .collect::<Vec<_>>();
+
+    if let Some(vcs_info) = vcs_info {
+        let path = format!("{}-{}/.cargo_vcs_info.json", pkg.name(),
+                           pkg.version());
+        let contents = json::encode(vcs_info).unwrap();
+        try!(append_bytes(&ar, &path, contents.as_bytes(), 0o644, mtime));
+    }
+
+    for &(ref relative, ref file) in files.iter() {
         try!(config.shell().verbose(|shell| {
             shell.status("Archiving", &relative)
         }));
-        let path = format!("{}-{}{}{}", pkg.name(), pkg.version(),
-                           path::MAIN_SEPARATOR, relative);
-        try!(ar.append_file(&path, &mut file).chain_error(|| {
-            internal(format!("could not archive source file `{}`", relative))
-        }));
+        let path = format!("{}-{}/{}", pkg.name(), pkg.version(), relative);
+        let mut contents = Vec::new();
+        try!(try!(File::open(file)).read_to_end(&mut contents));
+        if *relative == "Cargo.toml" {
+            let contents = try!(String::from_utf8(contents).map_err(|_| {
+                human(format!("{} is not valid UTF-8", file.display()))
+            }));
+            let normalized = try!(util::toml::to_packaged_manifest(
+                &contents, pkg.manifest_path(), pkg.manifest(), &root,
+                &packaged));
+            try!(append_bytes(&ar, &path, normalized.as_bytes(), 0o644, mtime));
+            try!(append_bytes(&ar, &format!("{}.orig", path),
+                              contents.as_bytes(), 0o644, mtime));
+            continue
+        }
+        let mode = if try!(is_executable(file)) {0o755} else {0o644};
+        try!(append_bytes(&ar, &path, &contents, mode, mtime));
     }
     try!(ar.finish());
     Ok(())
 }
 
+// The modification time recorded for every archive entry. This honors
+// `SOURCE_DATE_EPOCH` so that build systems can choose the timestamp.
+fn archive_mtime() -> CargoResult<u64> {
+    // Some tools complain about entries dated to the epoch itself, so the
+    // default is an arbitrary fixed date instead (2006-07-24).
+    const DEFAULT_MTIME: u64 = 1153704088;
+
+    match env::var("SOURCE_DATE_EPOCH") {
+        Ok(s) => s.trim().parse().map_err(|_| {
+            human(format!("invalid value for SOURCE_DATE_EPOCH: `{}`", s))
+        }),
+        Err(..) => Ok(DEFAULT_MTIME),
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> CargoResult<bool> {
+    use std::os::unix::prelude::*;
+    let mode = try!(fs::metadata(path)).permissions().mode();
+    Ok(mode & 0o111 != 0)
+}
+
+#[cfg(windows)]
+fn is_executable(_path: &Path) -> CargoResult<bool> {
+    Ok(false)
+}
+
+fn append_bytes<W: Write>(ar: &Archive<W>, path: &str, mut contents: &[u8],
+                          mode: u32, mtime: u64) -> CargoResult<()> {
+    let mut header = Header::new();
+    try!(header.set_path(path));
+    header.set_size(contents.len() as u64);
+    header.set_mode(mode);
+    header.set_mtime(mtime);
+    header.set_uid(0);
+    header.set_gid(0);
+    header.set_cksum();
+    try!(ar.append(&header, &mut contents).chain_error(|| {
+        internal(format!("could not archive source file `{}`", path))
+    }));
+    Ok(())
+}
+
+/// Returns the sha256 checksum of a packaged `.crate` file, as recorded by
+/// the registry when the package is published.
+pub fn package_checksum(path: &Path) -> CargoResult<String> {
+    let mut contents = Vec::new();
+    try!(try!(File::open(path)).read_to_end(&mut contents));
+    let mut state = Sha256::new();
+    state.update(&contents);
+    Ok(util::to_hex(&state.finish()))
+}
+
 fn run_verify(config: &Config, pkg: &Package, tar: &Path)
               -> CargoResult<()> {
     try!(config.shell().status("Verifying", pkg));
@@ -187,28 +443,10 @@ fn run_verify(config: &Config, pkg: &Package, tar: &Path)
     try!(archive.unpack(dst.parent().unwrap()));
     let manifest_path = dst.join("Cargo.toml");
 
-    // When packages are uploaded to the registry, all path dependencies are
-    // implicitly converted to registry-based dependencies, so we rewrite those
-    // dependencies here.
-    //
-    // We also make sure to point all paths at `dst` instead of the previous
-    // location that the package was originally read from. In locking the
-    // `SourceId` we're telling it that the corresponding `PathSource` will be
-    // considered updated and we won't actually read any packages.
-    let registry = try!(SourceId::for_central(config));
-    let precise = Some("locked".to_string());
-    let new_src = try!(SourceId::for_path(&dst)).with_precise(precise);
-    let new_pkgid = try!(PackageId::new(pkg.name(), pkg.version(), &new_src));
-    let new_summary = pkg.summary().clone().map_dependencies(|d| {
-        if !d.source_id().is_path() { return d }
-        d.clone_inner().set_source_id(registry.clone()).into_dependency()
-    });
-    let mut new_manifest = pkg.manifest().clone();
-    new_manifest.set_summary(new_summary.override_id(new_pkgid));
-    let new_pkg = Package::new(new_manifest, &manifest_path);
-
-    // Now that we've rewritten all our path dependencies, compile it!
-    try!(ops::compile_pkg(&new_pkg, None, &ops::CompileOptions {
+    // The packaged manifest no longer refers to any path dependencies, so the
+    // unpacked tarball is built exactly as it would be once downloaded from
+    // the registry.
+    try!(ops::compile(&manifest_path, &ops::CompileOptions {
         config: config,
         jobs: None,
         target: None,
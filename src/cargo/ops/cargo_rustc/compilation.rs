@@ -1,9 +1,9 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::ffi::OsStr;
 use std::path::PathBuf;
 use semver::Version;
 
-use core::{PackageId, Package, Target};
+use core::{PackageId, Package, Target, TargetKind};
 use util::{self, CargoResult, Config};
 
 use super::{CommandType, CommandPrototype};
@@ -11,13 +11,11 @@ use super::{CommandType, CommandPrototype};
 /// A structure returning the result of a compilation.
 pub struct Compilation<'cfg> {
     /// All libraries which were built for a package.
-    ///
-    /// This is currently used for passing --extern flags to rustdoc tests later
-    /// on.
     pub libraries: HashMap<PackageId, Vec<(Target, PathBuf)>>,
 
-    /// An array of all tests created during this compilation.
-    pub tests: Vec<(Package, String, PathBuf)>,
+    /// An array of all tests created during this compilation, along with the
+    /// kind and name of the target each of them was built from.
+    pub tests: Vec<(Package, TargetKind, String, PathBuf)>,
 
     /// An array of all binaries created.
     pub binaries: Vec<PathBuf>,
@@ -39,14 +37,29 @@ pub struct Compilation<'cfg> {
     /// be passed to future invocations of programs.
     pub extra_env: HashMap<PackageId, Vec<(String, String)>>,
 
-    pub to_doc_test: Vec<Package>,
+    /// The libraries whose documentation can be tested, along with what's
+    /// needed to do so.
+    pub to_doc_test: Vec<Doctest>,
 
-    /// Features enabled during this compilation.
-    pub cfgs: HashSet<String>,
+    /// The program, and its arguments, to run the executables for the target
+    /// through, if any.
+    pub target_runner: Option<(PathBuf, Vec<String>)>,
 
     config: &'cfg Config,
 }
 
+/// A target of a compilation whose documentation can be tested.
+pub struct Doctest {
+    pub package: Package,
+    pub target: Target,
+    /// The crates to pass with `--extern`: the library of the package and its
+    /// dependencies, including its dev-dependencies if they were built.
+    pub deps: Vec<(String, PathBuf)>,
+    /// The `--cfg` flags the package was compiled with, from its features and
+    /// from its build script.
+    pub cfgs: Vec<String>,
+}
+
 impl<'cfg> Compilation<'cfg> {
     pub fn new(config: &'cfg Config) -> Compilation<'cfg> {
         Compilation {
@@ -58,7 +71,7 @@ impl<'cfg> Compilation<'cfg> {
             binaries: Vec::new(),
             extra_env: HashMap::new(),
             to_doc_test: Vec::new(),
-            cfgs: HashSet::new(),
+            target_runner: None,
             config: config,
         }
     }
@@ -75,9 +88,24 @@ impl<'cfg> Compilation<'cfg> {
     }
 
     /// See `process`.
+    ///
+    /// If a runner is configured for the target the process runs it instead,
+    /// with `cmd` following the runner's own arguments.
     pub fn target_process<T: AsRef<OsStr>>(&self, cmd: T, pkg: &Package)
                                                -> CargoResult<CommandPrototype> {
-        self.process(CommandType::Target(cmd.as_ref().to_os_string()), pkg)
+        match self.target_runner {
+            Some((ref runner, ref args)) => {
+                let runner = runner.clone().into_os_string();
+                let mut process = try!(self.process(CommandType::Target(runner),
+                                                    pkg));
+                process.args(args).arg(cmd);
+                Ok(process)
+            }
+            None => {
+                self.process(CommandType::Target(cmd.as_ref().to_os_string()),
+                             pkg)
+            }
+        }
     }
 
     /// See `process`.
@@ -73,6 +73,8 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
         let engine = build_config.exec_engine.as_ref().cloned().unwrap_or({
             Arc::new(Box::new(ProcessEngine))
         });
+        let mut compilation = Compilation::new(config);
+        compilation.target_runner = build_config.target.runner.clone();
         Ok(Context {
             target_triple: target_triple,
             host: host,
@@ -85,7 +87,7 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
             target_exe: target_exe,
             host_dylib: host_dylib,
             host_exe: host_exe,
-            compilation: Compilation::new(config),
+            compilation: compilation,
             build_state: Arc::new(BuildState::new(&build_config, deps)),
             build_config: build_config,
             exec_engine: engine,
@@ -141,11 +143,13 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
     pub fn prepare(&mut self, root: &Package) -> CargoResult<()> {
         let _p = profile::start("preparing layout");
 
+        try!(self.host.lock(self.config));
         try!(self.host.prepare().chain_error(|| {
             internal(format!("couldn't prepare build directories"))
         }));
         match self.target {
             Some(ref mut target) => {
+                try!(target.lock(self.config));
                 try!(target.prepare().chain_error(|| {
                     internal(format!("couldn't prepare build directories"))
                 }));
@@ -377,6 +381,20 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
         return ret
     }
 
+    /// Returns the dependencies of the doc tests of the library `unit`.
+    ///
+    /// These are the dependencies of the library when it's compiled as a
+    /// test, which is what brings in its dev-dependencies, without the test
+    /// itself having to be compiled.
+    pub fn doc_test_deps(&self, unit: &Unit<'a>) -> Vec<Unit<'a>> {
+        let profile = if self.build_config.release {
+            &self.profiles.bench
+        } else {
+            &self.profiles.test
+        };
+        self.dep_targets(&Unit { profile: profile, ..*unit })
+    }
+
     /// Returns the dependencies needed to run a build script.
     ///
     /// The `unit` provided must represent an execution of a build script, and
@@ -43,6 +43,10 @@
 //!     # Hidden directory that holds all of the fingerprint files for all
 //!     # packages
 //!     .fingerprint/
+//!
+//!     # Lock file held by the Cargo process currently building into this
+//!     # directory
+//!     .cargo-lock
 //! ```
 
 use std::fs;
@@ -50,7 +54,8 @@ use std::io;
 use std::path::{PathBuf, Path};
 
 use core::Package;
-use util::Config;
+use util::{CargoResult, Config};
+use util::flock::{self, FileLock};
 use util::hex::short_hash;
 
 pub struct Layout {
@@ -60,6 +65,7 @@ pub struct Layout {
     build: PathBuf,
     fingerprint: PathBuf,
     examples: PathBuf,
+    lock: Option<FileLock>,
 }
 
 pub struct LayoutProxy<'a> {
@@ -89,9 +95,18 @@ impl Layout {
             fingerprint: root.join(".fingerprint"),
             examples: root.join("examples"),
             root: root,
+            lock: None,
         }
     }
 
+    /// Takes the lock on this directory for as long as the layout lives, so
+    /// other Cargo processes wait for this one to finish building into it.
+    pub fn lock(&mut self, config: &Config) -> CargoResult<()> {
+        let lock = try!(flock::exclusive(&self.root, "build directory", config));
+        self.lock = Some(lock);
+        Ok(())
+    }
+
     pub fn prepare(&mut self) -> io::Result<()> {
         if fs::metadata(&self.root).is_err() {
             try!(fs::create_dir_all(&self.root));
@@ -14,7 +14,7 @@ use util::{Config, internal, ChainError, profile, join_paths};
 use self::job::{Job, Work};
 use self::job_queue::JobQueue;
 
-pub use self::compilation::Compilation;
+pub use self::compilation::{Compilation, Doctest};
 pub use self::context::{Context, Unit};
 pub use self::engine::{CommandPrototype, CommandType, ExecEngine, ProcessEngine};
 pub use self::layout::{Layout, LayoutProxy};
@@ -42,12 +42,16 @@ pub struct BuildConfig {
     pub exec_engine: Option<Arc<Box<ExecEngine>>>,
     pub release: bool,
     pub doc_all: bool,
+    pub doc_test: bool,
 }
 
 #[derive(Clone, Default)]
 pub struct TargetConfig {
     pub ar: Option<PathBuf>,
     pub linker: Option<PathBuf>,
+    /// The program, and its arguments, through which the executables built
+    /// for this target are run.
+    pub runner: Option<(PathBuf, Vec<String>)>,
     pub overrides: HashMap<String, BuildOutput>,
 }
 
@@ -102,7 +106,21 @@ pub fn compile_targets<'a, 'cfg: 'a>(pkg_targets: &'a PackagesToBuild<'a>,
         // part of this, that's all done next as part of the `execute`
         // function which will run everything in order with proper
         // parallelism.
-        try!(compile(&mut cx, &mut queue, unit));
+        //
+        // Doc tests are compiled by rustdoc from the sources, so when only
+        // the doc tests are going to be run the binaries, examples and
+        // integration tests whose documentation is tested aren't compiled.
+        if !cx.build_config.doc_test || unit.target.is_lib() {
+            try!(compile(&mut cx, &mut queue, unit));
+        }
+
+        // Doc tests may use dev-dependencies, so build those as well when
+        // only the doc tests are going to be run.
+        if cx.build_config.doc_test && unit.target.doctested() {
+            for dep in cx.doc_test_deps(unit).iter() {
+                try!(compile(&mut cx, &mut queue, dep));
+            }
+        }
     }
 
     // Now that we've figured out everything that we're going to do, do it!
@@ -115,10 +133,22 @@ pub fn compile_targets<'a, 'cfg: 'a>(pkg_targets: &'a PackagesToBuild<'a>,
           .or_insert(Vec::new())
           .push(("OUT_DIR".to_string(), out_dir));
 
+        // A library is also compiled as a unit test, but only its plain
+        // build is doc tested, while any other target is only ever built
+        // once.
+        let doc_tested = unit.target.doctested() && !unit.profile.doc &&
+                         (!unit.target.is_lib() || !unit.profile.test);
+        if doc_tested {
+            let doctest = try!(doctest(&cx, unit));
+            cx.compilation.to_doc_test.push(doctest);
+        }
+        if !cx.compiled.contains(unit) { continue }
+
         for filename in try!(cx.target_filenames(unit)).iter() {
             let dst = cx.out_dir(unit).join(filename);
             if unit.profile.test {
                 cx.compilation.tests.push((unit.pkg.clone(),
+                                           unit.target.kind().clone(),
                                            unit.target.name().to_string(),
                                            dst));
             } else if unit.target.is_bin() || unit.target.is_example() {
@@ -148,17 +178,7 @@ pub fn compile_targets<'a, 'cfg: 'a>(pkg_targets: &'a PackagesToBuild<'a>,
         }
     }
 
-    let root_pkg = root.package_id();
-    if let Some(feats) = cx.resolve.features(root_pkg) {
-        cx.compilation.cfgs.extend(feats.iter().map(|feat| {
-            format!("feature=\"{}\"", feat)
-        }));
-    }
-
     for (&(ref pkg, _), output) in cx.build_state.outputs.lock().unwrap().iter() {
-        if pkg == root_pkg {
-            cx.compilation.cfgs.extend(output.cfgs.iter().cloned());
-        }
         let any_dylib = output.library_links.iter().any(|l| {
             !l.starts_with("static=") && !l.starts_with("framework=")
         });
@@ -170,6 +190,62 @@ pub fn compile_targets<'a, 'cfg: 'a>(pkg_targets: &'a PackagesToBuild<'a>,
     Ok(cx.compilation)
 }
 
+/// Gathers what's needed to run the doc tests of the target of `unit`.
+///
+/// The library of the package is among the dependencies of a binary, an
+/// example or an integration test, so their doc tests can use it.
+fn doctest<'a, 'cfg>(cx: &Context<'a, 'cfg>, unit: &Unit<'a>)
+                     -> CargoResult<Doctest> {
+    // Dev-dependencies are only passed along if they were built, which isn't
+    // the case for a plain `cargo build`.
+    let mut dep_units = vec![*unit];
+    dep_units.extend(cx.doc_test_deps(unit).into_iter());
+
+    let mut deps = Vec::new();
+    for dep in dep_units.iter() {
+        if !dep.target.is_lib() || dep.profile.test || dep.profile.doc ||
+           !cx.compiled.contains(dep) {
+            continue
+        }
+        for filename in try!(cx.target_filenames(dep)).iter() {
+            let path = cx.out_dir(dep).join(filename);
+            // Note that we can *only* doctest rlib outputs here.  A staticlib
+            // output cannot be linked by the compiler (it just doesn't do
+            // that). A dylib output, however, can be linked by the compiler,
+            // but will always fail. Currently all dylibs are built as "static
+            // dylibs" where the standard library is statically linked into
+            // the dylib. The doc tests fail, however, for now as they try to
+            // link the standard library dynamically as well, causing
+            // problems. As a result we only pass `--extern` for rlib deps and
+            // skip out on all other artifacts.
+            if path.extension() != Some(OsStr::new("rlib")) &&
+               !dep.target.for_host() {
+                continue
+            }
+            let dep = (dep.target.crate_name(), path);
+            if !deps.contains(&dep) {
+                deps.push(dep);
+            }
+        }
+    }
+
+    let mut cfgs = Vec::new();
+    if let Some(feats) = cx.resolve.features(unit.pkg.package_id()) {
+        cfgs.extend(feats.iter().map(|feat| format!("feature=\"{}\"", feat)));
+    }
+    let key = (unit.pkg.package_id().clone(), unit.kind);
+    if let Some(output) = cx.build_state.outputs.lock().unwrap().get(&key) {
+        cfgs.extend(output.cfgs.iter().cloned());
+    }
+
+    Ok(Doctest {
+        package: unit.pkg.clone(),
+        target: unit.target.clone(),
+        deps: deps,
+        cfgs: cfgs,
+    })
+}
+
 fn compile<'a, 'cfg: 'a>(cx: &mut Context<'a, 'cfg>,
                          jobs: &mut JobQueue<'a>,
                          unit: &Unit<'a>) -> CargoResult<()> {
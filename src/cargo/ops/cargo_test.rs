@@ -1,18 +1,31 @@
-use std::ffi::{OsString, OsStr};
+use std::ffi::OsString;
+use std::io::prelude::*;
+use std::io;
 use std::path::Path;
+use std::sync::mpsc::channel;
 
-use ops::{self, ExecEngine, ProcessEngine, Compilation};
+use crossbeam;
+use time;
+
+use core::{Package, TargetKind};
+use ops::{self, ExecEngine, ProcessEngine, Compilation, CommandPrototype};
+use ops::cargo_compile::build_jobs;
+use ops::test_report::{self, ReportFormat, TestRun};
 use util::{self, CargoResult, CargoTestError, ProcessError};
 
 pub struct TestOptions<'a> {
     pub compile_opts: ops::CompileOptions<'a>,
     pub no_run: bool,
     pub no_fail_fast: bool,
+    /// Run as many test binaries at the same time as there are jobs.
+    pub parallel: bool,
+    /// Only run the doc tests.
+    pub only_doc: bool,
+    /// Where to write a report of the outcome of the tests, and in which
+    /// format.
+    pub report: Option<(&'a Path, ReportFormat)>,
 }
 
-
-
-#[allow(deprecated)] // connect => join in 1.3
 pub fn run_tests(manifest_path: &Path,
                  options: &TestOptions,
                  test_args: &[String]) -> CargoResult<Option<CargoTestError>> {
@@ -21,27 +34,18 @@ pub fn run_tests(manifest_path: &Path,
     if options.no_run {
         return Ok(None)
     }
-    let mut errors = try!(run_unit_tests(options, test_args, &compilation));
-
-    // If we have an error and want to fail fast, return
-    if errors.len() > 0 && !options.no_fail_fast {
-        return Ok(Some(CargoTestError::new(errors)))
-    }
-
-    // If a specific test was requested or we're not running any tests at all,
-    // don't run any doc tests.
-    if let ops::CompileFilter::Only { .. } = options.compile_opts.filter {
-        match errors.len() {
-            0 => return Ok(None),
-            _ => return Ok(Some(CargoTestError::new(errors)))
-        }
-    }
-
-    errors.extend(try!(run_doc_tests(options, test_args, &compilation)));
-    if errors.len() == 0 {
-        Ok(None)
+    let mut runs = Vec::new();
+    let errors = if options.only_doc {
+        try!(run_doc_tests(options, test_args, &compilation, &mut runs))
     } else {
-        Ok(Some(CargoTestError::new(errors)))
+        try!(run_all_tests(options, test_args, &compilation, &mut runs))
+    };
+    if let Some((path, format)) = options.report {
+        try!(test_report::write(path, format, &runs));
+    }
+    match errors.len() {
+        0 => Ok(None),
+        _ => Ok(Some(CargoTestError::new(errors))),
     }
 }
 
@@ -51,20 +55,46 @@ pub fn run_benches(manifest_path: &Path,
     let mut args = args.to_vec();
     args.push("--bench".to_string());
     let compilation = try!(compile_tests(manifest_path, options));
-    let errors = try!(run_unit_tests(options, &args, &compilation));
+    let mut runs = Vec::new();
+    let errors = try!(run_unit_tests(options, &args, &compilation, &mut runs));
+    if let Some((path, format)) = options.report {
+        try!(test_report::write(path, format, &runs));
+    }
     match errors.len() {
         0 => Ok(None),
         _ => Ok(Some(CargoTestError::new(errors))),
     }
 }
 
+fn run_all_tests(options: &TestOptions,
+                 test_args: &[String],
+                 compilation: &Compilation,
+                 runs: &mut Vec<TestRun>) -> CargoResult<Vec<ProcessError>> {
+    let mut errors = try!(run_unit_tests(options, test_args, compilation,
+                                         runs));
+
+    // If we have an error and want to fail fast, return
+    if errors.len() > 0 && !options.no_fail_fast {
+        return Ok(errors)
+    }
+
+    // If a specific test was requested or we're not running any tests at all,
+    // don't run any doc tests.
+    if let ops::CompileFilter::Only { .. } = options.compile_opts.filter {
+        return Ok(errors)
+    }
+
+    errors.extend(try!(run_doc_tests(options, test_args, compilation, runs)));
+    Ok(errors)
+}
+
 fn compile_tests<'a>(manifest_path: &Path,
                      options: &TestOptions<'a>)
                      -> CargoResult<Compilation<'a>> {
     let mut compilation = try!(ops::compile(manifest_path,
                                             &options.compile_opts));
     compilation.tests.sort_by(|a, b| {
-        (a.0.package_id(), &a.1).cmp(&(b.0.package_id(), &b.1))
+        (a.0.package_id(), &a.2).cmp(&(b.0.package_id(), &b.2))
     });
     Ok(compilation)
 }
@@ -72,14 +102,23 @@ fn compile_tests<'a>(manifest_path: &Path,
 /// Run the unit and integration tests of a project.
 fn run_unit_tests(options: &TestOptions,
                   test_args: &[String],
-                  compilation: &Compilation)
+                  compilation: &Compilation,
+                  runs: &mut Vec<TestRun>)
                   -> CargoResult<Vec<ProcessError>> {
     let config = options.compile_opts.config;
     let cwd = options.compile_opts.config.cwd();
 
+    if options.parallel {
+        let jobs = try!(build_jobs(config, options.compile_opts.jobs));
+        if jobs > 1 {
+            return run_unit_tests_parallel(options, test_args, compilation,
+                                           jobs as usize, runs)
+        }
+    }
+
     let mut errors = Vec::new();
 
-    for &(ref pkg, _, ref exe) in &compilation.tests {
+    for &(ref pkg, ref kind, ref test, ref exe) in &compilation.tests {
         let to_display = match util::without_prefix(exe, &cwd) {
             Some(path) => path,
             None => &**exe,
@@ -93,7 +132,7 @@ fn run_unit_tests(options: &TestOptions,
             shell.status("Running", cmd.to_string())
         }));
 
-        if let Err(e) = ExecEngine::exec(&mut ProcessEngine, cmd) {
+        if let Err(e) = run(options, cmd, pkg, Some(kind), test, runs) {
             errors.push(e);
             if !options.no_fail_fast {
                 break
@@ -103,76 +142,175 @@ fn run_unit_tests(options: &TestOptions,
     Ok(errors)
 }
 
+/// Runs the unit and integration tests like `run_unit_tests`, but with up to
+/// `jobs` test binaries running at the same time. The output of each binary
+/// is buffered and printed all at once when it exits.
+fn run_unit_tests_parallel(options: &TestOptions,
+                           test_args: &[String],
+                           compilation: &Compilation,
+                           jobs: usize,
+                           runs: &mut Vec<TestRun>)
+                           -> CargoResult<Vec<ProcessError>> {
+    let config = options.compile_opts.config;
+    let cwd = options.compile_opts.config.cwd();
+
+    let mut cmds = Vec::new();
+    for &(ref pkg, _, _, ref exe) in &compilation.tests {
+        let mut cmd = try!(compilation.target_process(exe, pkg));
+        cmd.args(test_args);
+        cmds.push((cmd.to_string(), cmd.into_process_builder()));
+    }
+
+    let (tx, rx) = channel();
+    let mut results = cmds.iter().map(|_| None).collect::<Vec<_>>();
+    let mut next = 0;
+    let mut active = 0;
+    let mut failed = false;
+    try!(crossbeam::scope(|scope| -> CargoResult<()> {
+        loop {
+            // Without --no-fail-fast no more binaries are started after one
+            // has failed, but those which are running are waited for.
+            while active < jobs && next < cmds.len() &&
+                  (!failed || options.no_fail_fast) {
+                let tx = tx.clone();
+                let (i, builder) = (next, &cmds[next].1);
+                scope.spawn(move || {
+                    let start = time::precise_time_ns();
+                    let output = builder.build_command().output();
+                    tx.send((i, output, time::precise_time_ns() - start)).unwrap();
+                });
+                next += 1;
+                active += 1;
+            }
+            if active == 0 {
+                break
+            }
+
+            let (i, output, duration) = rx.recv().unwrap();
+            active -= 1;
+            let (ref pkg, ref kind, ref test, ref exe) = compilation.tests[i];
+            let (ref cmd, ref builder) = cmds[i];
+            let to_display = match util::without_prefix(exe, &cwd) {
+                Some(path) => path,
+                None => &**exe,
+            };
+            try!(config.shell().concise(|shell| {
+                shell.status("Running", to_display.display().to_string())
+            }));
+            try!(config.shell().verbose(|shell| {
+                shell.status("Running", cmd)
+            }));
+
+            let mut test_run = options.report.map(|_| {
+                TestRun::new(pkg, Some(kind), test)
+            });
+            let result = match output {
+                Ok(output) => {
+                    try!(io::stdout().write_all(&output.stdout));
+                    try!(io::stderr().write_all(&output.stderr));
+                    if let Some(ref mut test_run) = test_run {
+                        for line in String::from_utf8_lossy(&output.stdout).lines() {
+                            test_run.record_line(line);
+                        }
+                    }
+                    if output.status.success() {
+                        Ok(())
+                    } else {
+                        Err(builder.exit_error(&output.status, None))
+                    }
+                }
+                Err(e) => Err(builder.spawn_error(e)),
+            };
+            if let Some(ref mut test_run) = test_run {
+                test_run.finish(duration, &result);
+            }
+            failed = failed || result.is_err();
+            results[i] = Some((result, test_run));
+        }
+        Ok(())
+    }));
+
+    // Report the outcome in the same order as when running sequentially.
+    let mut errors = Vec::new();
+    for (result, test_run) in results.into_iter().filter_map(|r| r) {
+        runs.extend(test_run.into_iter());
+        if let Err(e) = result {
+            errors.push(e);
+        }
+    }
+    Ok(errors)
+}
+
 #[allow(deprecated)] // connect => join in 1.3
 fn run_doc_tests(options: &TestOptions,
                  test_args: &[String],
-                 compilation: &Compilation)
+                 compilation: &Compilation,
+                 runs: &mut Vec<TestRun>)
                  -> CargoResult<Vec<ProcessError>> {
     let mut errors = Vec::new();
     let config = options.compile_opts.config;
 
-    let libs = compilation.to_doc_test.iter().map(|package| {
-        (package, package.targets().iter().filter(|t| t.doctested())
-                         .map(|t| (t.src_path(), t.name(), t.crate_name())))
-    });
+    for doctest in compilation.to_doc_test.iter() {
+        let (package, target) = (&doctest.package, &doctest.target);
+        try!(config.shell().status("Doc-tests", target.name()));
+        let mut p = try!(compilation.rustdoc_process(package));
+        p.arg("--test").arg(target.src_path())
+         .arg("--crate-name").arg(&target.crate_name());
 
-    for (package, tests) in libs {
-        for (lib, name, crate_name) in tests {
-            try!(config.shell().status("Doc-tests", name));
-            let mut p = try!(compilation.rustdoc_process(package));
-            p.arg("--test").arg(lib)
-             .arg("--crate-name").arg(&crate_name);
-
-            for &rust_dep in &[&compilation.deps_output, &compilation.root_output] {
-                let mut arg = OsString::from("dependency=");
-                arg.push(rust_dep);
-                p.arg("-L").arg(arg);
-            }
-            for native_dep in compilation.native_dirs.values() {
-                p.arg("-L").arg(native_dep);
-            }
+        for &rust_dep in &[&compilation.deps_output, &compilation.root_output] {
+            let mut arg = OsString::from("dependency=");
+            arg.push(rust_dep);
+            p.arg("-L").arg(arg);
+        }
+        for native_dep in compilation.native_dirs.values() {
+            p.arg("-L").arg(native_dep);
+        }
 
-            if test_args.len() > 0 {
-                p.arg("--test-args").arg(&test_args.connect(" "));
-            }
+        if test_args.len() > 0 {
+            p.arg("--test-args").arg(&test_args.connect(" "));
+        }
 
-            for cfg in compilation.cfgs.iter() {
-                p.arg("--cfg").arg(cfg);
-            }
+        for cfg in doctest.cfgs.iter() {
+            p.arg("--cfg").arg(cfg);
+        }
 
-            for (_, libs) in compilation.libraries.iter() {
-                for &(ref target, ref lib) in libs.iter() {
-                    // Note that we can *only* doctest rlib outputs here.  A
-                    // staticlib output cannot be linked by the compiler (it just
-                    // doesn't do that). A dylib output, however, can be linked by
-                    // the compiler, but will always fail. Currently all dylibs are
-                    // built as "static dylibs" where the standard library is
-                    // statically linked into the dylib. The doc tests fail,
-                    // however, for now as they try to link the standard library
-                    // dynamically as well, causing problems. As a result we only
-                    // pass `--extern` for rlib deps and skip out on all other
-                    // artifacts.
-                    if lib.extension() != Some(OsStr::new("rlib")) &&
-                       !target.for_host() {
-                        continue
-                    }
-                    let mut arg = OsString::from(target.crate_name());
-                    arg.push("=");
-                    arg.push(lib);
-                    p.arg("--extern").arg(&arg);
-                }
-            }
+        for &(ref crate_name, ref lib) in doctest.deps.iter() {
+            let mut arg = OsString::from(crate_name);
+            arg.push("=");
+            arg.push(lib);
+            p.arg("--extern").arg(&arg);
+        }
 
-            try!(config.shell().verbose(|shell| {
-                shell.status("Running", p.to_string())
-            }));
-            if let Err(e) = ExecEngine::exec(&mut ProcessEngine, p) {
-                errors.push(e);
-                if !options.no_fail_fast {
-                    return Ok(errors);
-                }
+        try!(config.shell().verbose(|shell| {
+            shell.status("Running", p.to_string())
+        }));
+        if let Err(e) = run(options, p, package, None, target.name(), runs) {
+            errors.push(e);
+            if !options.no_fail_fast {
+                return Ok(errors);
             }
         }
     }
     Ok(errors)
 }
+
+/// Runs a test binary or rustdoc. If a report was requested, the tests it
+/// runs and their outcome are recorded in `runs` as well.
+fn run(options: &TestOptions, cmd: CommandPrototype, pkg: &Package,
+       kind: Option<&TargetKind>, target: &str, runs: &mut Vec<TestRun>)
+       -> Result<(), ProcessError> {
+    if options.report.is_none() {
+        return ExecEngine::exec(&mut ProcessEngine, cmd)
+    }
+
+    let mut test_run = TestRun::new(pkg, kind, target);
+    let start = time::precise_time_ns();
+    let builder = cmd.into_process_builder();
+    let result = builder.exec_with_streaming(&mut |line: &str| {
+        println!("{}", line);
+        test_run.record_line(line);
+    });
+    test_run.finish(time::precise_time_ns() - start, &result);
+    runs.push(test_run);
+    result
+}
@@ -39,6 +39,12 @@ pub fn write_pkg_lockfile(pkg: &Package, resolve: &Resolve) -> CargoResult<()> {
 }
 
 pub fn write_lockfile(dst: &Path, resolve: &Resolve) -> CargoResult<()> {
+    try!(paths::write(dst, resolve_to_string(resolve).as_bytes()));
+    Ok(())
+}
+
+/// Render `resolve` exactly as it is written to a lockfile.
+pub fn resolve_to_string(resolve: &Resolve) -> String {
     let mut e = Encoder::new();
     resolve.encode(&mut e).unwrap();
 
@@ -68,8 +74,7 @@ pub fn write_lockfile(dst: &Path, resolve: &Resolve) -> CargoResult<()> {
         None => {}
     }
 
-    try!(paths::write(dst, out.as_bytes()));
-    Ok(())
+    out
 }
 
 fn emit_package(dep: &toml::Table, out: &mut String) {
@@ -1,6 +1,7 @@
+pub use self::cargo_cache::{cache_gc, CacheGcOptions};
 pub use self::cargo_clean::{clean, CleanOptions};
 pub use self::cargo_compile::{compile, compile_pkg, CompileOptions};
-pub use self::cargo_compile::{CompileFilter, CompileMode};
+pub use self::cargo_compile::{CompileFilter, CompileMode, FilterRule};
 pub use self::cargo_read_manifest::{read_manifest,read_package,read_packages};
 pub use self::cargo_rustc::{compile_targets, Compilation, Layout, Kind, Unit};
 pub use self::cargo_rustc::{Context, LayoutProxy};
@@ -8,22 +9,26 @@ pub use self::cargo_rustc::{BuildOutput, BuildConfig, TargetConfig};
 pub use self::cargo_rustc::{CommandType, CommandPrototype, ExecEngine, ProcessEngine};
 pub use self::cargo_run::run;
 pub use self::cargo_install::{install, install_list, uninstall};
-pub use self::cargo_new::{new, NewOptions, VersionControl};
+pub use self::cargo_new::{new, init, NewOptions, VersionControl};
+pub use self::cargo_outdated::{outdated, print_outdated, OutdatedPackage};
 pub use self::cargo_doc::{doc, DocOptions};
 pub use self::cargo_generate_lockfile::{generate_lockfile};
 pub use self::cargo_generate_lockfile::{update_lockfile};
-pub use self::cargo_generate_lockfile::UpdateOptions;
+pub use self::cargo_generate_lockfile::{UpdateOptions, LockfileChange};
 pub use self::lockfile::{load_lockfile, load_pkg_lockfile};
-pub use self::lockfile::{write_lockfile, write_pkg_lockfile};
+pub use self::lockfile::{write_lockfile, write_pkg_lockfile, resolve_to_string};
 pub use self::cargo_test::{run_tests, run_benches, TestOptions};
-pub use self::cargo_package::package;
+pub use self::test_report::ReportFormat;
+pub use self::cargo_package::{package, package_checksum, PackageOpts};
 pub use self::registry::{publish, registry_configuration, RegistryConfig};
+pub use self::registry::PublishOpts;
 pub use self::registry::{registry_login, search, http_proxy_exists, http_handle};
 pub use self::registry::{modify_owners, yank, OwnersOptions};
 pub use self::cargo_fetch::{fetch, get_resolved_packages};
 pub use self::cargo_pkgid::pkgid;
 pub use self::resolve::{resolve_pkg, resolve_with_previous};
 
+mod cargo_cache;
 mod cargo_clean;
 mod cargo_compile;
 mod cargo_doc;
@@ -31,6 +36,7 @@ mod cargo_fetch;
 mod cargo_generate_lockfile;
 mod cargo_install;
 mod cargo_new;
+mod cargo_outdated;
 mod cargo_package;
 mod cargo_pkgid;
 mod cargo_read_manifest;
@@ -40,3 +46,4 @@ mod cargo_test;
 mod lockfile;
 mod registry;
 mod resolve;
+mod test_report;
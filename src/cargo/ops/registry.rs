@@ -1,13 +1,14 @@
-use std::collections::HashMap;
 use std::env;
 use std::fs;
 use std::io::prelude::*;
 use std::iter::repeat;
 use std::path::{Path, PathBuf};
+use std::process::Stdio;
 
 use curl::http;
 use git2;
 use registry::{Registry, NewCrate, NewCrateDependency};
+use rustc_serialize::json;
 use term::color::BLACK;
 
 use core::source::Source;
@@ -18,33 +19,86 @@ use ops;
 use sources::{RegistrySource};
 use util::config;
 use util::paths;
-use util::{CargoResult, human, ChainError, ToUrl};
-use util::config::{Config, ConfigValue, Location};
+use util::{self, CargoResult, human, ChainError, ToUrl};
+use util::config::Config;
 use util::important_paths::find_root_manifest_for_cwd;
 
 pub struct RegistryConfig {
     pub index: Option<String>,
     pub token: Option<String>,
+    /// A program and its arguments printing the API token on stdout, run
+    /// when no token is stored in Cargo's credentials or configuration.
+    pub credential_provider: Option<(PathBuf, Vec<String>)>,
 }
 
+impl RegistryConfig {
+    /// The API token for the registry whose index is `index`, which is the
+    /// stored token if there is one, and otherwise the one printed by the
+    /// credential provider.
+    pub fn token(&self, index: &str) -> CargoResult<Option<String>> {
+        match (&self.token, &self.credential_provider) {
+            (&Some(ref token), _) => Ok(Some(token.clone())),
+            (&None, &Some((ref program, ref args))) => {
+                Ok(Some(try!(run_credential_provider(program, args, index))))
+            }
+            (&None, &None) => Ok(None),
+        }
+    }
+}
+
+pub struct PublishOpts {
+    pub token: Option<String>,
+    pub index: Option<String>,
+    pub verify: bool,
+    pub allow_dirty: bool,
+    /// Perform every step of publishing except the upload itself.
+    pub dry_run: bool,
+}
+
+// Limits enforced by crates.io on uploaded packages, checked locally so that
+// problems are reported before anything is sent.
+const MAX_NAME_LENGTH: usize = 64;
+const MAX_KEYWORDS: usize = 5;
+const MAX_KEYWORD_LENGTH: usize = 20;
+const MAX_CATEGORIES: usize = 5;
+const MAX_UPLOAD_SIZE: u64 = 10 * 1024 * 1024;
+
 pub fn publish(manifest_path: &Path,
                config: &Config,
-               token: Option<String>,
-               index: Option<String>,
-               verify: bool) -> CargoResult<()> {
+               opts: &PublishOpts) -> CargoResult<()> {
     let pkg = try!(Package::for_path(&manifest_path, config));
 
-    let (mut registry, reg_id) = try!(registry(config, token, index));
+    // A dry run never uploads, so it doesn't need an API token.
+    let (mut registry, reg_id) = try!(registry(config, opts.token.clone(),
+                                               opts.index.clone(),
+                                               !opts.dry_run));
     try!(verify_dependencies(&pkg, &reg_id));
 
     // Prepare a tarball, with a non-surpressable warning if metadata
     // is missing since this is being put online.
-    let tarball = try!(ops::package(manifest_path, config, verify,
-                                    false, true)).unwrap();
+    let tarball = try!(ops::package(manifest_path, &ops::PackageOpts {
+        config: config,
+        verify: opts.verify,
+        list: false,
+        check_metadata: true,
+        allow_dirty: opts.allow_dirty,
+    })).unwrap();
+
+    let krate = try!(new_crate(&pkg));
+    let size = try!(fs::metadata(&tarball)).len();
+    try!(check_limits(&krate, size));
+
+    if opts.dry_run {
+        try!(print_summary(&krate, &tarball, size, config));
+        try!(config.shell().warn("warning: aborting upload due to dry run"));
+        return Ok(())
+    }
 
     // Upload said tarball to the specified destination
     try!(config.shell().status("Uploading", pkg.package_id().to_string()));
-    try!(transmit(&pkg, &tarball, &mut registry));
+    try!(registry.publish(&krate, &tarball).map_err(|e| {
+        human(e.to_string())
+    }));
 
     Ok(())
 }
@@ -70,8 +124,8 @@ fn verify_dependencies(pkg: &Package, registry_src: &SourceId)
     Ok(())
 }
 
-fn transmit(pkg: &Package, tarball: &Path, registry: &mut Registry)
-            -> CargoResult<()> {
+// Build the metadata which is uploaded alongside the tarball.
+fn new_crate(pkg: &Package) -> CargoResult<NewCrate> {
     let deps = pkg.dependencies().iter().map(|dep| {
         NewCrateDependency {
             optional: dep.is_optional(),
@@ -91,9 +145,14 @@ fn transmit(pkg: &Package, tarball: &Path, registry: &mut Registry)
     let ManifestMetadata {
         ref authors, ref description, ref homepage, ref documentation,
         ref keywords, ref readme, ref repository, ref license, ref license_file,
+        ref categories, ref badges,
     } = *manifest.metadata();
     let readme = match *readme {
-        Some(ref readme) => Some(try!(paths::read(&pkg.root().join(readme)))),
+        Some(ref readme) => {
+            Some(try!(paths::read(&pkg.root().join(readme)).chain_error(|| {
+                human(format!("failed to read the readme file `{}`", readme))
+            })))
+        }
         None => None,
     };
     match *license_file {
@@ -105,7 +164,7 @@ fn transmit(pkg: &Package, tarball: &Path, registry: &mut Registry)
         }
         None => {}
     }
-    registry.publish(&NewCrate {
+    Ok(NewCrate {
         name: pkg.name().to_string(),
         vers: pkg.version().to_string(),
         deps: deps,
@@ -119,27 +178,138 @@ fn transmit(pkg: &Package, tarball: &Path, registry: &mut Registry)
         repository: repository.clone(),
         license: license.clone(),
         license_file: license_file.clone(),
-    }, tarball).map_err(|e| {
-        human(e.to_string())
+        categories: categories.clone(),
+        badges: badges.clone(),
+        links: manifest.links().map(|s| s.to_string()),
     })
 }
 
+#[allow(deprecated)] // connect => join in 1.3
+fn check_limits(krate: &NewCrate, size: u64) -> CargoResult<()> {
+    if krate.name.len() > MAX_NAME_LENGTH {
+        return Err(human(format!("the package name `{}` is longer than the \
+                                  maximum of {} characters", krate.name,
+                                 MAX_NAME_LENGTH)))
+    }
+
+    let mut missing = Vec::new();
+    if krate.description.as_ref().map_or(true, |s| s.is_empty()) {
+        missing.push("description");
+    }
+    if krate.license.as_ref().map_or(true, |s| s.is_empty()) &&
+       krate.license_file.as_ref().map_or(true, |s| s.is_empty()) {
+        missing.push("license");
+    }
+    if !missing.is_empty() {
+        return Err(human(format!("missing or empty metadata fields: {}\n\
+                                  See http://doc.crates.io/manifest.html#\
+                                  package-metadata for more info.",
+                                 missing.connect(", "))))
+    }
+
+    if krate.keywords.len() > MAX_KEYWORDS {
+        return Err(human(format!("the package has {} keywords, but at most \
+                                  {} are allowed", krate.keywords.len(),
+                                 MAX_KEYWORDS)))
+    }
+    for keyword in krate.keywords.iter() {
+        let valid = keyword.len() <= MAX_KEYWORD_LENGTH &&
+            keyword.chars().next().map_or(false, |c| c.is_alphabetic()) &&
+            keyword.chars().all(|c| {
+                c.is_alphanumeric() || c == '-' || c == '_'
+            });
+        if !valid {
+            return Err(human(format!("invalid keyword `{}`: keywords must \
+                                      start with a letter, contain only \
+                                      letters, numbers, `-` or `_`, and be \
+                                      at most {} characters long", keyword,
+                                     MAX_KEYWORD_LENGTH)))
+        }
+    }
+
+    if krate.categories.len() > MAX_CATEGORIES {
+        return Err(human(format!("the package has {} categories, but at most \
+                                  {} are allowed", krate.categories.len(),
+                                 MAX_CATEGORIES)))
+    }
+
+    if size > MAX_UPLOAD_SIZE {
+        return Err(human(format!("the package tarball is {} bytes, which \
+                                  exceeds the maximum upload size of {} \
+                                  bytes", size, MAX_UPLOAD_SIZE)))
+    }
+    Ok(())
+}
+
+#[allow(deprecated)] // connect => join in 1.3
+fn print_summary(krate: &NewCrate, tarball: &Path, size: u64,
+                 config: &Config) -> CargoResult<()> {
+    let opt = |s: &Option<String>| s.clone().unwrap_or("-".to_string());
+    let tarball = match util::without_prefix(tarball, config.cwd()) {
+        Some(path) => path.to_path_buf(),
+        None => tarball.to_path_buf(),
+    };
+    let mut shell = config.shell();
+    try!(shell.say(format!("{} v{} would be uploaded with:", krate.name,
+                           krate.vers), BLACK));
+    try!(shell.say(format!("    tarball: {} ({} bytes)", tarball.display(),
+                           size), BLACK));
+    try!(shell.say(format!("    dependencies: {}", krate.deps.len()), BLACK));
+    try!(shell.say(format!("    features: {}", krate.features.len()), BLACK));
+    try!(shell.say(format!("    authors: {}", krate.authors.connect(", ")),
+                   BLACK));
+    try!(shell.say(format!("    description: {}", opt(&krate.description)),
+                   BLACK));
+    try!(shell.say(format!("    license: {}",
+                           opt(&krate.license.clone()
+                                    .or(krate.license_file.clone()))),
+                   BLACK));
+    try!(shell.say(format!("    keywords: {}", krate.keywords.connect(", ")),
+                   BLACK));
+    try!(shell.say(format!("    categories: {}",
+                           krate.categories.connect(", ")), BLACK));
+    try!(shell.say(format!("    badges: {}",
+                           krate.badges.keys().cloned().collect::<Vec<_>>()
+                                .connect(", ")), BLACK));
+    try!(shell.say(format!("    readme: {}",
+                           if krate.readme.is_some() {"yes"} else {"no"}),
+                   BLACK));
+    try!(shell.verbose(|shell| {
+        shell.say(format!("    payload: {}", json::encode(krate).unwrap()),
+                  BLACK)
+    }));
+    Ok(())
+}
+
 pub fn registry_configuration(config: &Config) -> CargoResult<RegistryConfig> {
     let index = try!(config.get_string("registry.index")).map(|p| p.0);
     let token = try!(config.get_string("registry.token")).map(|p| p.0);
-    Ok(RegistryConfig { index: index, token: token })
+    let provider = try!(config.get_program("registry.credential-provider"));
+    Ok(RegistryConfig {
+        index: index,
+        token: token,
+        credential_provider: provider,
+    })
 }
 
+/// Returns a handle to the registry whose index is `index`, or the configured
+/// one, along with its source id.
+///
+/// The API token is only looked up, possibly running the credential
+/// provider, if the handle is going to be used to `authenticate`.
 pub fn registry(config: &Config,
                 token: Option<String>,
-                index: Option<String>) -> CargoResult<(Registry, SourceId)> {
+                index: Option<String>,
+                authenticate: bool) -> CargoResult<(Registry, SourceId)> {
     // Parse all configuration options
-    let RegistryConfig {
-        token: token_config,
-        index: index_config,
-    } = try!(registry_configuration(config));
-    let token = token.or(token_config);
-    let index = index.or(index_config).unwrap_or(RegistrySource::default_url());
+    let reg_cfg = try!(registry_configuration(config));
+    let index = index.or(reg_cfg.index.clone())
+                     .unwrap_or(RegistrySource::default_url());
+    let token = match token {
+        Some(token) => Some(token),
+        None if authenticate => try!(reg_cfg.token(&index)),
+        None => None,
+    };
     let index = try!(index.to_url().map_err(human));
     let sid = SourceId::for_registry(&index);
     let api_host = {
@@ -153,6 +323,37 @@ pub fn registry(config: &Config,
     Ok((Registry::new_handle(api_host, token, handle), sid))
 }
 
+// Credential providers are commands (such as a script reading the token from
+// the system's keyring) which print the API token for the registry whose
+// index is in `CARGO_REGISTRY_INDEX` on stdout. Only stdout is captured, so
+// they can still prompt for a password on the terminal.
+fn run_credential_provider(program: &Path, args: &[String], index: &str)
+                           -> CargoResult<String> {
+    let mut cmd = try!(util::process(program));
+    cmd.args(args).env("CARGO_REGISTRY_INDEX", index);
+    let mut command = cmd.build_command();
+    command.stdout(Stdio::piped());
+    let output = command.spawn().and_then(|child| child.wait_with_output())
+                        .map_err(|e| cmd.spawn_error(e))
+                        .and_then(|output| {
+        if output.status.success() {
+            Ok(output)
+        } else {
+            Err(cmd.exit_error(&output.status, None))
+        }
+    });
+    let output = try!(output.chain_error(|| {
+        human(format!("failed to get the API token from the credential \
+                       provider {}", cmd))
+    }));
+    let token = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if token.is_empty() {
+        return Err(human(format!("the credential provider {} did not print \
+                                  an API token", cmd)))
+    }
+    Ok(token)
+}
+
 /// Create a new HTTP handle with appropriate global configuration for cargo.
 pub fn http_handle(config: &Config) -> CargoResult<http::Handle> {
     // The timeout option for libcurl by default times out the entire transfer,
@@ -224,19 +425,7 @@ pub fn http_timeout(config: &Config) -> CargoResult<Option<i64>> {
 }
 
 pub fn registry_login(config: &Config, token: String) -> CargoResult<()> {
-    let RegistryConfig { index, token: _ } = try!(registry_configuration(config));
-    let mut map = HashMap::new();
-    let p = config.cwd().to_path_buf();
-    match index {
-        Some(index) => {
-            map.insert("index".to_string(), ConfigValue::String(index, p.clone()));
-        }
-        None => {}
-    }
-    map.insert("token".to_string(), ConfigValue::String(token, p));
-
-    config::set_config(config, Location::Global, "registry",
-                       ConfigValue::Table(map, PathBuf::from(".")))
+    config::save_credentials(config, token)
 }
 
 pub struct OwnersOptions {
@@ -258,8 +447,13 @@ pub fn modify_owners(config: &Config, opts: &OwnersOptions) -> CargoResult<()> {
         }
     };
 
+    for owner in opts.to_add.iter().chain(opts.to_remove.iter())
+                     .flat_map(|v| v.iter()) {
+        try!(check_owner(owner));
+    }
+
     let (mut registry, _) = try!(registry(config, opts.token.clone(),
-                                          opts.index.clone()));
+                                          opts.index.clone(), true));
 
     match opts.to_add {
         Some(ref v) => {
@@ -291,6 +485,9 @@ pub fn modify_owners(config: &Config, opts: &OwnersOptions) -> CargoResult<()> {
         }));
         for owner in owners.iter() {
             print!("{}", owner.login);
+            if owner.kind.as_ref().map(|s| &s[..]) == Some("team") {
+                print!(" [team]");
+            }
             match (owner.name.as_ref(), owner.email.as_ref()) {
                 (Some(name), Some(email)) => println!(" ({} <{}>)", name, email),
                 (Some(s), None) |
@@ -303,6 +500,21 @@ pub fn modify_owners(config: &Config, opts: &OwnersOptions) -> CargoResult<()> {
     Ok(())
 }
 
+// Owners are either user logins or teams, which are named by the host the
+// team lives on followed by the organization and the team itself.
+fn check_owner(owner: &str) -> CargoResult<()> {
+    if !owner.contains(':') {
+        return Ok(())
+    }
+    let parts = owner.split(':').collect::<Vec<_>>();
+    if parts.len() != 3 || parts[0] != "github" ||
+       parts[1].is_empty() || parts[2].is_empty() {
+        return Err(human(format!("invalid team owner `{}`, teams must be \
+                                  specified as `github:org:team`", owner)))
+    }
+    Ok(())
+}
+
 pub fn yank(config: &Config,
             krate: Option<String>,
             version: Option<String>,
@@ -322,7 +534,7 @@ pub fn yank(config: &Config,
         None => return Err(human("a version must be specified to yank"))
     };
 
-    let (mut registry, _) = try!(registry(config, token, index));
+    let (mut registry, _) = try!(registry(config, token, index, true));
 
     if undo {
         try!(config.shell().status("Unyank", format!("{}:{}", name, version)));
@@ -348,7 +560,7 @@ pub fn search(query: &str, config: &Config, index: Option<String>) -> CargoResul
         }
     }
 
-    let (mut registry, _) = try!(registry(config, None, index));
+    let (mut registry, _) = try!(registry(config, None, index, false));
     let crates = try!(registry.search(query).map_err(|e| {
         human(format!("failed to retrieve search results from the registry: {}", e))
     }));
@@ -4,25 +4,92 @@ use core::{Package, PackageId, SourceId};
 use core::registry::PackageRegistry;
 use core::resolver::{self, Resolve, Method};
 use ops;
-use util::CargoResult;
+use sources::RegistrySource;
+use util::{CargoResult, Config, human};
 
 /// Resolve all dependencies for the specified `package` using the previous
 /// lockfile as a guide if present.
 ///
 /// This function will also write the result of resolution as a new
 /// lockfile.
+///
+/// If any registry package in the lockfile has since been yanked a warning is
+/// printed, or an error is returned if the configuration denies yanked
+/// packages.
+///
+/// If the configuration requires the lockfile to be used as-is, an error is
+/// returned when it is missing or when resolution would change it. The
+/// lockfile of a package which isn't a local path is only used in that case.
 pub fn resolve_pkg(registry: &mut PackageRegistry, package: &Package)
                    -> CargoResult<Resolve> {
-    let prev = try!(ops::load_pkg_lockfile(package));
+    let locked = registry.config().locked();
+    let lockfile = package.root().join("Cargo.lock");
+    // Packages which aren't local, such as those built by `cargo install`,
+    // may ship the lock file they were published with. It's only followed
+    // when asked to, otherwise their dependencies are resolved afresh.
+    let prev = if locked || package.package_id().source_id().is_path() {
+        try!(ops::load_pkg_lockfile(package))
+    } else {
+        None
+    };
+    if locked && prev.is_none() {
+        return Err(human(format!("the lock file {} needs to be present when \
+                                  --locked is passed", lockfile.display())))
+    }
     let resolve = try!(resolve_with_previous(registry, package,
                                              Method::Everything,
                                              prev.as_ref(), None));
+    if let Some(ref prev) = prev {
+        try!(check_yanked(&resolve, registry.config()));
+        if locked && ops::resolve_to_string(prev) !=
+                     ops::resolve_to_string(&resolve) {
+            return Err(human(format!("the lock file {} needs to be updated \
+                                      but --locked was passed to prevent \
+                                      this", lockfile.display())))
+        }
+    }
     if package.package_id().source_id().is_path() {
         try!(ops::write_pkg_lockfile(package, &resolve));
     }
     Ok(resolve)
 }
 
+/// Look up each locked registry package of `resolve` in its registry's index
+/// and report those which have been yanked.
+#[allow(deprecated)] // connect => join in 1.3
+fn check_yanked(resolve: &Resolve, config: &Config) -> CargoResult<()> {
+    let mut sources = HashMap::new();
+    let mut yanked = Vec::new();
+    for pkg in resolve.iter() {
+        if !pkg.source_id().is_registry() { continue }
+        let source = sources.entry(pkg.source_id().clone()).or_insert_with(|| {
+            RegistrySource::new(pkg.source_id(), config)
+        });
+        if try!(source.is_yanked(pkg)) {
+            yanked.push(pkg);
+        }
+    }
+    if yanked.is_empty() {
+        return Ok(())
+    }
+    yanked.sort();
+
+    let list = yanked.iter().map(|pkg| format!("    {}", pkg))
+                     .collect::<Vec<_>>().connect("\n");
+    let update = yanked.iter().map(|pkg| format!("-p {}", pkg.name()))
+                       .collect::<Vec<_>>().connect(" ");
+    let msg = format!("the following packages in Cargo.lock have been yanked \
+                       from their registry:\n{}\n\
+                       consider running `cargo update {}` to move off of them",
+                      list, update);
+    if config.deny_yanked() {
+        Err(human(msg))
+    } else {
+        try!(config.shell().warn(format!("warning: {}", msg)));
+        Ok(())
+    }
+}
+
 /// Resolve all dependencies for a package using an optional previous instance
 /// of resolve to guide the resolution process.
 ///
@@ -0,0 +1,190 @@
+//! Reports of the outcome of `cargo test`.
+//!
+//! When a report is requested, the output of every test binary and doc test
+//! run is scanned for the lines in which the test harness announces the result
+//! of each test (`test foo::bar ... ok`). Together with the exit status and
+//! the running time of each binary, these are written to a single JSON or
+//! JUnit XML file once all tests have run.
+
+use std::path::Path;
+
+use rustc_serialize::{Decodable, Decoder};
+use rustc_serialize::json;
+
+use core::{Package, TargetKind};
+use util::{CargoResult, ChainError, ProcessError, human, paths};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReportFormat { Json, Junit }
+
+impl Decodable for ReportFormat {
+    fn decode<D: Decoder>(d: &mut D) -> Result<ReportFormat, D::Error> {
+        Ok(match &try!(d.read_str())[..] {
+            "json" => ReportFormat::Json,
+            "junit" => ReportFormat::Junit,
+            n => {
+                let err = format!("could not decode '{}' as a report format", n);
+                return Err(d.error(&err));
+            }
+        })
+    }
+}
+
+/// The outcome of running one test binary, or the doc tests of one target.
+#[derive(RustcEncodable)]
+pub struct TestRun {
+    package: String,
+    target: String,
+    kind: String,
+    /// The time it took to run, in seconds.
+    duration: f64,
+    exit_code: Option<i32>,
+    success: bool,
+    tests: Vec<TestCase>,
+}
+
+#[derive(RustcEncodable)]
+struct TestCase {
+    name: String,
+    /// One of `ok`, `failed`, `ignored` or `bench`.
+    result: String,
+}
+
+#[derive(RustcEncodable)]
+struct Report<'a> {
+    binaries: &'a [TestRun],
+}
+
+impl TestRun {
+    /// Starts recording a run of the tests of `target`, where `kind` is the
+    /// kind of the target or `None` for its doc tests.
+    pub fn new(pkg: &Package, kind: Option<&TargetKind>, target: &str)
+               -> TestRun {
+        let kind = match kind {
+            Some(&TargetKind::Lib(..)) => "lib",
+            Some(&TargetKind::Bin) => "bin",
+            Some(&TargetKind::Test) => "test",
+            Some(&TargetKind::Bench) => "bench",
+            Some(&TargetKind::Example) => "example",
+            Some(&TargetKind::CustomBuild) => "custom-build",
+            None => "doc",
+        };
+        TestRun {
+            package: pkg.package_id().to_string(),
+            target: target.to_string(),
+            kind: kind.to_string(),
+            duration: 0.0,
+            exit_code: None,
+            success: false,
+            tests: Vec::new(),
+        }
+    }
+
+    /// Records the result of a test if `line` is the test harness printing
+    /// one.
+    pub fn record_line(&mut self, line: &str) {
+        if !line.starts_with("test ") {
+            return
+        }
+        let line = &line["test ".len()..];
+        let (name, result) = match line.rfind(" ... ") {
+            Some(i) => (&line[..i], &line[i + " ... ".len()..]),
+            None => return,
+        };
+        let result = match result {
+            "ok" => "ok",
+            "FAILED" => "failed",
+            "ignored" => "ignored",
+            s if s.starts_with("bench:") => "bench",
+            _ => return,
+        };
+        self.tests.push(TestCase {
+            name: name.to_string(),
+            result: result.to_string(),
+        });
+    }
+
+    /// Records how the run ended, and how many nanoseconds it took.
+    pub fn finish(&mut self, duration_ns: u64, result: &Result<(), ProcessError>) {
+        self.duration = duration_ns as f64 / 1_000_000_000.0;
+        self.success = result.is_ok();
+        self.exit_code = match *result {
+            Ok(()) => Some(0),
+            Err(ref e) => e.exit.as_ref().and_then(|s| s.code()),
+        };
+    }
+
+    fn count(&self, result: &str) -> usize {
+        self.tests.iter().filter(|t| t.result == result).count()
+    }
+}
+
+/// Writes the report of all of the given runs to `path`.
+pub fn write(path: &Path, format: ReportFormat, runs: &[TestRun])
+             -> CargoResult<()> {
+    let contents = match format {
+        ReportFormat::Json => json::encode(&Report { binaries: runs }).unwrap(),
+        ReportFormat::Junit => junit(runs),
+    };
+    paths::write(path, contents.as_bytes()).chain_error(|| {
+        human(format!("failed to write the test report to `{}`",
+                      path.display()))
+    })
+}
+
+fn junit(runs: &[TestRun]) -> String {
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<testsuites>\n");
+    for run in runs {
+        let failures = run.count("failed");
+        // A binary which failed without any failing test crashed or couldn't
+        // be run at all, which is reported as an error.
+        let errors = if !run.success && failures == 0 {1} else {0};
+        out.push_str(&format!("  <testsuite name=\"{} {}\" package=\"{}\" \
+                                tests=\"{}\" failures=\"{}\" errors=\"{}\" \
+                                skipped=\"{}\" time=\"{:.3}\">\n",
+                              escape(&run.kind), escape(&run.target),
+                              escape(&run.package), run.tests.len() + errors,
+                              failures, errors, run.count("ignored"),
+                              run.duration));
+        for test in run.tests.iter() {
+            out.push_str(&format!("    <testcase classname=\"{}\" name=\"{}\"",
+                                  escape(&run.target), escape(&test.name)));
+            match &test.result[..] {
+                "failed" => out.push_str(">\n      <failure/>\n    </testcase>\n"),
+                "ignored" => out.push_str(">\n      <skipped/>\n    </testcase>\n"),
+                _ => out.push_str("/>\n"),
+            }
+        }
+        if errors > 0 {
+            let status = match run.exit_code {
+                Some(code) => format!("exit code {}", code),
+                None => "no exit code".to_string(),
+            };
+            out.push_str(&format!("    <testcase classname=\"{0}\" name=\"{0}\">\n",
+                                  escape(&run.target)));
+            out.push_str(&format!("      <error message=\"the test binary failed \
+                                   with {}\"/>\n", status));
+            out.push_str("    </testcase>\n");
+        }
+        out.push_str("  </testsuite>\n");
+    }
+    out.push_str("</testsuites>\n");
+    out
+}
+
+fn escape(s: &str) -> String {
+    let mut ret = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => ret.push_str("&amp;"),
+            '<' => ret.push_str("&lt;"),
+            '>' => ret.push_str("&gt;"),
+            '"' => ret.push_str("&quot;"),
+            '\'' => ret.push_str("&apos;"),
+            c => ret.push(c),
+        }
+    }
+    ret
+}
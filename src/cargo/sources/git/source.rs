@@ -1,14 +1,14 @@
 use std::fmt::{self, Debug, Formatter};
 use std::hash::{Hash, Hasher, SipHasher};
 use std::mem;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use url::{self, Url};
 
 use core::source::{Source, SourceId};
 use core::GitReference;
 use core::{Package, PackageId, Summary, Registry, Dependency};
-use util::{CargoResult, Config, to_hex};
+use util::{CargoResult, Config, to_hex, last_use, flock};
 use sources::PathSource;
 use sources::git::utils::{GitRemote, GitRevision};
 
@@ -68,6 +68,56 @@ impl<'cfg> GitSource<'cfg> {
 
     pub fn url(&self) -> &Url { self.remote.url() }
 
+    /// Fetches the repository and checks out the requested revision without
+    /// reading any packages from it, returning the path of the checkout.
+    pub fn checkout(&mut self) -> CargoResult<&Path> {
+        try!(last_use::record(&self.db_path));
+        try!(last_use::record(&self.checkout_path));
+        let db_dir = self.config.git_db_path();
+        let lock = try!(flock::shared(&db_dir, "the git database", self.config));
+        let actual_rev = self.remote.rev_for(&self.db_path, &self.reference);
+        let should_update = actual_rev.is_err() ||
+                            self.source_id.precise().is_none();
+
+        let (repo, actual_rev, _lock) = if should_update {
+            drop(lock);
+            let lock = try!(flock::exclusive(&db_dir, "the git database",
+                                             self.config));
+            try!(self.config.shell().status("Updating",
+                format!("git repository `{}`", self.remote.url())));
+
+            trace!("updating git source `{:?}`", self.remote);
+            let reference = self.source_id.git_reference().unwrap();
+            let repo = try!(self.remote.checkout(&self.db_path, reference,
+                                                 self.config));
+            match repo.rev_for(&self.reference) {
+                Ok(rev) => (repo, rev, lock),
+
+                // A locked revision may no longer be reachable from the branch
+                // or tag it was resolved from (e.g. after a force push), so
+                // fall back to fetching everything the remote has.
+                Err(..) if self.source_id.precise().is_some() => {
+                    let repo = try!(self.remote.checkout_all(&self.db_path,
+                                                             self.config));
+                    let rev = try!(repo.rev_for(&self.reference));
+                    (repo, rev, lock)
+                }
+                Err(e) => return Err(e),
+            }
+        } else {
+            (try!(self.remote.db_at(&self.db_path)), actual_rev.unwrap(), lock)
+        };
+
+        // The database stays locked while checking out from it.
+        let _checkout_lock = try!(flock::exclusive(&self.config.git_checkout_path(),
+                                                   "the git checkouts",
+                                                   self.config));
+        try!(repo.copy_to(actual_rev.clone(), &self.checkout_path,
+                          self.config));
+        self.rev = Some(actual_rev);
+        Ok(&self.checkout_path)
+    }
+
     pub fn read_packages(&mut self) -> CargoResult<Vec<Package>> {
         if self.path_source.is_none() {
             try!(self.update());
@@ -169,30 +219,14 @@ impl<'cfg> Registry for GitSource<'cfg> {
 
 impl<'cfg> Source for GitSource<'cfg> {
     fn update(&mut self) -> CargoResult<()> {
-        let actual_rev = self.remote.rev_for(&self.db_path, &self.reference);
-        let should_update = actual_rev.is_err() ||
-                            self.source_id.precise().is_none();
-
-        let (repo, actual_rev) = if should_update {
-            try!(self.config.shell().status("Updating",
-                format!("git repository `{}`", self.remote.url())));
-
-            trace!("updating git source `{:?}`", self.remote);
-            let repo = try!(self.remote.checkout(&self.db_path));
-            let rev = try!(repo.rev_for(&self.reference));
-            (repo, rev)
-        } else {
-            (try!(self.remote.db_at(&self.db_path)), actual_rev.unwrap())
-        };
-
-        try!(repo.copy_to(actual_rev.clone(), &self.checkout_path));
+        try!(self.checkout());
+        let actual_rev = self.rev.clone().unwrap();
 
         let source_id = self.source_id.with_precise(Some(actual_rev.to_string()));
         let path_source = PathSource::new(&self.checkout_path, &source_id,
                                           self.config);
 
         self.path_source = Some(path_source);
-        self.rev = Some(actual_rev);
         self.path_source.as_mut().unwrap().update()
     }
 
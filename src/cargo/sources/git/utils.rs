@@ -7,7 +7,7 @@ use url::Url;
 use git2::{self, ObjectType};
 
 use core::GitReference;
-use util::{CargoResult, ChainError, human, ToUrl, internal};
+use util::{CargoResult, ChainError, Config, human, ToUrl, internal, process};
 
 #[derive(PartialEq, Clone, Debug)]
 pub struct GitRevision(git2::Oid);
@@ -108,16 +108,85 @@ impl GitRemote {
         db.rev_for(reference)
     }
 
-    pub fn checkout(&self, into: &Path) -> CargoResult<GitDatabase> {
+    /// Fetch the parts of this remote needed to resolve `reference` into the
+    /// database at `into`, creating the database if it doesn't exist yet.
+    ///
+    /// Only the branch or tag named by `reference` is fetched. A revision
+    /// can't be mapped to a ref, so all branches and tags are fetched for
+    /// those, unless the git CLI is used and the server agrees to send just
+    /// that commit. libgit2 does not support shallow fetches, so with it the
+    /// full history of the selected refs is always downloaded.
+    pub fn checkout(&self, into: &Path, reference: &GitReference,
+                    config: &Config) -> CargoResult<GitDatabase> {
+        let refspecs = match *reference {
+            GitReference::Branch(ref b) => {
+                vec![format!("refs/heads/{0}:refs/heads/{0}", b)]
+            }
+            GitReference::Tag(ref t) => {
+                vec![format!("refs/tags/{0}:refs/tags/{0}", t)]
+            }
+            GitReference::Rev(ref rev) => {
+                if is_commit_id(rev) && try!(fetch_with_cli_enabled(config)) {
+                    // Many servers refuse requests for arbitrary commits, in
+                    // which case everything is fetched instead.
+                    match self.checkout_shallow(into, rev) {
+                        Ok(db) => return Ok(db),
+                        Err(e) => info!("shallow fetch of {} failed: {}", rev, e),
+                    }
+                }
+                all_refspecs()
+            }
+        };
+        self.checkout_refspecs(into, &refspecs, config)
+    }
+
+    /// Fetch only the commit `rev`, without its history, into the database at
+    /// `into` with the git CLI.
+    ///
+    /// The commit isn't on any branch or tag of the database, so it's kept
+    /// under `REV_REFS` for checkouts to fetch it from there.
+    fn checkout_shallow(&self, into: &Path, rev: &str)
+                        -> CargoResult<GitDatabase> {
+        let repo = match git2::Repository::open(into) {
+            Ok(repo) => repo,
+            Err(..) => {
+                if fs::metadata(&into).is_ok() {
+                    try!(fs::remove_dir_all(into));
+                }
+                try!(fs::create_dir_all(into));
+                try!(git2::Repository::init_bare(into))
+            }
+        };
+        let refspec = format!("+{0}:{1}{0}", rev, REV_REFS);
+        try!(fetch_with_cli(&repo, &self.url.to_string(), &[refspec], true));
+        Ok(GitDatabase {
+            remote: self.clone(),
+            path: into.to_path_buf(),
+            repo: repo,
+        })
+    }
+
+    /// Fetch all branches and tags of this remote into the database at
+    /// `into`.
+    ///
+    /// This is the fallback for when a locked revision is no longer reachable
+    /// from the branch or tag it was originally resolved from.
+    pub fn checkout_all(&self, into: &Path, config: &Config)
+                        -> CargoResult<GitDatabase> {
+        self.checkout_refspecs(into, &all_refspecs(), config)
+    }
+
+    fn checkout_refspecs(&self, into: &Path, refspecs: &[String],
+                         config: &Config) -> CargoResult<GitDatabase> {
         let repo = match git2::Repository::open(into) {
             Ok(repo) => {
-                try!(self.fetch_into(&repo).chain_error(|| {
+                try!(self.fetch_into(&repo, refspecs, config).chain_error(|| {
                     human(format!("failed to fetch into {}", into.display()))
                 }));
                 repo
             }
             Err(..) => {
-                try!(self.clone_into(into).chain_error(|| {
+                try!(self.clone_into(into, refspecs, config).chain_error(|| {
                     human(format!("failed to clone into: {}", into.display()))
                 }))
             }
@@ -139,21 +208,22 @@ impl GitRemote {
         })
     }
 
-    fn fetch_into(&self, dst: &git2::Repository) -> CargoResult<()> {
+    fn fetch_into(&self, dst: &git2::Repository, refspecs: &[String],
+                  config: &Config) -> CargoResult<()> {
         // Create a local anonymous remote in the repository to fetch the url
         let url = self.url.to_string();
-        let refspec = "refs/heads/*:refs/heads/*";
-        fetch(dst, &url, refspec)
+        fetch(dst, &url, refspecs, config)
     }
 
-    fn clone_into(&self, dst: &Path) -> CargoResult<git2::Repository> {
+    fn clone_into(&self, dst: &Path, refspecs: &[String], config: &Config)
+                  -> CargoResult<git2::Repository> {
         let url = self.url.to_string();
         if fs::metadata(&dst).is_ok() {
             try!(fs::remove_dir_all(dst));
         }
         try!(fs::create_dir_all(dst));
         let repo = try!(git2::Repository::init_bare(dst));
-        try!(fetch(&repo, &url, "refs/heads/*:refs/heads/*"));
+        try!(fetch(&repo, &url, refspecs, config));
         Ok(repo)
     }
 }
@@ -163,21 +233,21 @@ impl GitDatabase {
         &self.path
     }
 
-    pub fn copy_to(&self, rev: GitRevision, dest: &Path)
+    pub fn copy_to(&self, rev: GitRevision, dest: &Path, config: &Config)
                    -> CargoResult<GitCheckout> {
         let checkout = match git2::Repository::open(dest) {
             Ok(repo) => {
                 let checkout = GitCheckout::new(dest, self, rev, repo);
                 if !checkout.is_fresh() {
-                    try!(checkout.fetch());
+                    try!(checkout.fetch(config));
                     try!(checkout.reset());
                     assert!(checkout.is_fresh());
                 }
                 checkout
             }
-            Err(..) => try!(GitCheckout::clone_into(dest, self, rev)),
+            Err(..) => try!(GitCheckout::clone_into(dest, self, rev, config)),
         };
-        try!(checkout.update_submodules().chain_error(|| {
+        try!(checkout.update_submodules(config).chain_error(|| {
             internal("failed to update submodules")
         }));
         Ok(checkout)
@@ -234,16 +304,17 @@ impl<'a> GitCheckout<'a> {
     }
 
     fn clone_into(into: &Path, database: &'a GitDatabase,
-                  revision: GitRevision)
+                  revision: GitRevision, config: &Config)
                   -> CargoResult<GitCheckout<'a>>
     {
-        let repo = try!(GitCheckout::clone_repo(database.path(), into));
+        let repo = try!(GitCheckout::clone_repo(database.path(), into, config));
         let checkout = GitCheckout::new(into, database, revision, repo);
         try!(checkout.reset());
         Ok(checkout)
     }
 
-    fn clone_repo(source: &Path, into: &Path) -> CargoResult<git2::Repository> {
+    fn clone_repo(source: &Path, into: &Path, config: &Config)
+                  -> CargoResult<git2::Repository> {
         let dirname = into.parent().unwrap();
 
         try!(fs::create_dir_all(&dirname).chain_error(|| {
@@ -256,9 +327,13 @@ impl<'a> GitCheckout<'a> {
             }));
         }
 
+        // The database may only contain the tag, branch or commit this
+        // checkout was requested for, so rather than a plain clone (which only
+        // follows the remote's HEAD) fetch every ref it has.
         let url = try!(source.to_url().map_err(human));
         let url = url.to_string();
-        let repo = try!(git2::Repository::clone(&url, into).chain_error(|| {
+        let repo = try!(git2::Repository::init(into));
+        try!(fetch(&repo, &url, &database_refspecs(), config).chain_error(|| {
             internal(format!("failed to clone {} into {}", source.display(),
                              into.display()))
         }));
@@ -275,12 +350,11 @@ impl<'a> GitCheckout<'a> {
         }
     }
 
-    fn fetch(&self) -> CargoResult<()> {
+    fn fetch(&self, config: &Config) -> CargoResult<()> {
         info!("fetch {}", self.repo.path().display());
         let url = try!(self.database.path.to_url().map_err(human));
         let url = url.to_string();
-        let refspec = "refs/heads/*:refs/heads/*";
-        try!(fetch(&self.repo, &url, refspec));
+        try!(fetch(&self.repo, &url, &database_refspecs(), config));
         Ok(())
     }
 
@@ -302,10 +376,11 @@ impl<'a> GitCheckout<'a> {
         Ok(())
     }
 
-    fn update_submodules(&self) -> CargoResult<()> {
-        return update_submodules(&self.repo);
+    fn update_submodules(&self, config: &Config) -> CargoResult<()> {
+        return update_submodules(&self.repo, config);
 
-        fn update_submodules(repo: &git2::Repository) -> CargoResult<()> {
+        fn update_submodules(repo: &git2::Repository, config: &Config)
+                             -> CargoResult<()> {
             info!("update submodules for: {:?}", repo.workdir().unwrap());
 
             for mut child in try!(repo.submodules()).into_iter() {
@@ -337,21 +412,22 @@ impl<'a> GitCheckout<'a> {
                         repo
                     }
                     Err(..) => {
+                        // The fetch below pulls in everything, so there's no
+                        // need to have libgit2 clone over the network here.
                         let path = repo.workdir().unwrap().join(child.path());
-                        try!(git2::Repository::clone(url, &path))
+                        try!(git2::Repository::init(&path))
                     }
                 };
 
                 // Fetch data from origin and reset to the head commit
-                let refspec = "refs/heads/*:refs/heads/*";
-                try!(fetch(&repo, url, refspec).chain_error(|| {
+                try!(fetch(&repo, url, &all_refspecs(), config).chain_error(|| {
                     internal(format!("failed to fetch submodule `{}` from {}",
                                      child.name().unwrap_or(""), url))
                 }));
 
                 let obj = try!(repo.find_object(head, None));
                 try!(repo.reset(&obj, git2::ResetType::Hard, None));
-                try!(update_submodules(&repo));
+                try!(update_submodules(&repo, config));
             }
             Ok(())
         }
@@ -420,8 +496,47 @@ fn with_authentication<T, F>(url: &str, cfg: &git2::Config, mut f: F)
     }
 }
 
-pub fn fetch(repo: &git2::Repository, url: &str,
-             refspec: &str) -> CargoResult<()> {
+/// Where commits fetched on their own are kept in a database.
+const REV_REFS: &'static str = "refs/cargo/rev/";
+
+/// Refspecs which fetch every branch and tag of a remote.
+fn all_refspecs() -> Vec<String> {
+    vec!["refs/heads/*:refs/heads/*".to_string(),
+         "refs/tags/*:refs/tags/*".to_string()]
+}
+
+/// Refspecs which fetch everything a checkout may need from a database.
+fn database_refspecs() -> Vec<String> {
+    let mut refspecs = all_refspecs();
+    refspecs.push(format!("+{0}*:{0}*", REV_REFS));
+    refspecs
+}
+
+/// Whether `rev` is the full id of a commit, which is the only kind of
+/// revision a server can be asked for directly.
+fn is_commit_id(rev: &str) -> bool {
+    rev.len() == 40 && rev.chars().all(|c| c.is_digit(16))
+}
+
+fn fetch_with_cli_enabled(config: &Config) -> CargoResult<bool> {
+    Ok(try!(config.get_bool("net.git-fetch-with-cli")).map(|(b, _)| b)
+                                                      .unwrap_or(false))
+}
+
+/// Fetch `refspecs` from `url` into `repo`.
+///
+/// Tags are not fetched automatically, they need to be requested explicitly
+/// through a refspec.
+///
+/// If `net.git-fetch-with-cli` is set the system `git` binary is used instead
+/// of libgit2, picking up its configuration for SSH, URL rewriting, proxies
+/// and credentials.
+pub fn fetch(repo: &git2::Repository, url: &str, refspecs: &[String],
+             config: &Config) -> CargoResult<()> {
+    if try!(fetch_with_cli_enabled(config)) {
+        return fetch_with_cli(repo, url, refspecs, false)
+    }
+
     // Create a local anonymous remote in the repository to fetch the url
 
     with_authentication(url, &try!(repo.config()), |f| {
@@ -430,8 +545,47 @@ pub fn fetch(repo: &git2::Repository, url: &str,
         let mut remote = try!(repo.remote_anonymous(&url));
         let mut opts = git2::FetchOptions::new();
         opts.remote_callbacks(cb)
-            .download_tags(git2::AutotagOption::All);
-        try!(remote.fetch(&[refspec], Some(&mut opts), None));
+            .download_tags(git2::AutotagOption::None);
+        let refspecs = refspecs.iter().map(|s| &s[..]).collect::<Vec<_>>();
+        try!(remote.fetch(&refspecs, Some(&mut opts), None));
         Ok(())
     })
 }
+
+/// Fetch `refspecs` with the system `git` binary. With `shallow` only the
+/// fetched commits themselves are downloaded, not their history.
+fn fetch_with_cli(repo: &git2::Repository, url: &str, refspecs: &[String],
+                  shallow: bool) -> CargoResult<()> {
+    // `--update-head-ok` is needed as checkouts fetch into their own checked
+    // out branch.
+    let mut cmd = try!(process("git"));
+    cmd.arg("fetch")
+       .arg("--force")
+       .arg("--no-tags")
+       .arg("--update-head-ok");
+    if shallow {
+        cmd.arg("--depth").arg("1");
+    } else if fs::metadata(repo.path().join("shallow")).is_ok() {
+        // An earlier shallow fetch is missing history which other revisions
+        // may need.
+        cmd.arg("--unshallow");
+    }
+    cmd.arg(url)
+       .args(refspecs)
+       .env("GIT_DIR", repo.path())
+       .cwd(repo.path());
+
+    // A shallow fetch is only an attempt, so the error of a server refusing
+    // it is kept quiet. Otherwise the output of git is not captured, so any
+    // errors and authentication prompts are passed straight through to the
+    // user.
+    let res = if shallow {
+        cmd.exec_with_output().map(|_| ())
+    } else {
+        cmd.exec()
+    };
+    try!(res.chain_error(|| {
+        human(format!("failed to fetch `{}` with the git CLI", url))
+    }));
+    Ok(())
+}
@@ -157,6 +157,11 @@
 //!         registry1-<hash>/<pkg>-<version>/...
 //!         ...
 //! ```
+//!
+//! Every time a tarball or an unpacked source is used, a marker next to it in
+//! a `.last-use` folder is touched (see `util::last_use`), which is how `cargo
+//! cache gc` knows which entries of `cache` and `src` haven't been needed for
+//! a while and can be removed again.
 
 use std::collections::HashMap;
 use std::fs::{self, File};
@@ -175,7 +180,7 @@ use core::{Source, SourceId, PackageId, Package, Summary, Registry};
 use core::dependency::{Dependency, DependencyInner, Kind};
 use sources::{PathSource, git};
 use util::{CargoResult, Config, internal, ChainError, ToUrl, human};
-use util::{hex, Sha256, paths};
+use util::{hex, Sha256, paths, last_use, flock};
 use ops;
 
 static DEFAULT: &'static str = "https://github.com/rust-lang/crates.io-index";
@@ -265,11 +270,19 @@ impl<'cfg> RegistrySource<'cfg> {
     ///
     /// This requires that the index has been at least checked out.
     pub fn config(&self) -> CargoResult<RegistryConfig> {
+        let _lock = try!(self.lock_index_shared());
         let contents = try!(paths::read(&self.checkout_path.join("config.json")));
         let config = try!(json::decode(&contents));
         Ok(config)
     }
 
+    /// Takes a shared lock on the index, which keeps other Cargo processes from
+    /// updating it while it's being read.
+    fn lock_index_shared(&self) -> CargoResult<flock::FileLock> {
+        flock::shared(&self.config.registry_index_path(), "the registry index",
+                      self.config)
+    }
+
     /// Open the git repository for the index of the registry.
     ///
     /// This will attempt to open an existing checkout, and failing that it will
@@ -299,6 +312,7 @@ impl<'cfg> RegistrySource<'cfg> {
         // TODO: should discover filename from the S3 redirect
         let filename = format!("{}-{}.crate", pkg.name(), pkg.version());
         let dst = self.cache_path.join(&filename);
+        try!(last_use::record(&dst));
         if fs::metadata(&dst).is_ok() { return Ok(dst) }
         try!(self.config.shell().status("Downloading", pkg));
 
@@ -354,6 +368,7 @@ impl<'cfg> RegistrySource<'cfg> {
                       -> CargoResult<PathBuf> {
         let dst = self.src_path.join(&format!("{}-{}", pkg.name(),
                                               pkg.version()));
+        try!(last_use::record(&dst));
         if fs::metadata(&dst.join(".cargo-ok")).is_ok() { return Ok(dst) }
 
         try!(fs::create_dir_all(dst.parent().unwrap()));
@@ -381,10 +396,12 @@ impl<'cfg> RegistrySource<'cfg> {
                      .join(&fs_name[2..4])
                      .join(&fs_name),
         };
+        let lock = try!(self.lock_index_shared());
         let summaries = match File::open(&path) {
             Ok(mut f) => {
                 let mut contents = String::new();
                 try!(f.read_to_string(&mut contents));
+                drop(lock);
                 let ret: CargoResult<Vec<(Summary, bool)>>;
                 ret = contents.lines().filter(|l| l.trim().len() > 0)
                               .map(|l| self.parse_registry_package(l))
@@ -403,6 +420,17 @@ impl<'cfg> RegistrySource<'cfg> {
         Ok(self.cache.get(name).unwrap())
     }
 
+    /// Returns whether the given package has been yanked from this registry.
+    ///
+    /// Packages which aren't listed in the index at all are not considered
+    /// yanked.
+    pub fn is_yanked(&mut self, pkg: &PackageId) -> CargoResult<bool> {
+        let summaries = try!(self.summaries(pkg.name()));
+        Ok(summaries.iter().any(|&(ref s, yanked)| {
+            yanked && s.version() == pkg.version()
+        }))
+    }
+
     /// Parse a line from the registry's index file into a Summary for a
     /// package.
     ///
@@ -455,14 +483,16 @@ impl<'cfg> RegistrySource<'cfg> {
     fn do_update(&mut self) -> CargoResult<()> {
         if self.updated { return Ok(()) }
 
+        let _lock = try!(flock::exclusive(&self.config.registry_index_path(),
+                                          "the registry index", self.config));
         try!(self.config.shell().status("Updating",
              format!("registry `{}`", self.source_id.url())));
         let repo = try!(self.open());
 
         // git fetch origin
         let url = self.source_id.url().to_string();
-        let refspec = "refs/heads/*:refs/remotes/origin/*";
-        try!(git::fetch(&repo, &url, refspec).chain_error(|| {
+        let refspec = "refs/heads/*:refs/remotes/origin/*".to_string();
+        try!(git::fetch(&repo, &url, &[refspec], self.config).chain_error(|| {
             internal(format!("failed to fetch `{}`", url))
         }));
 
@@ -543,6 +573,8 @@ impl<'cfg> Source for RegistrySource<'cfg> {
             url.path_mut().unwrap().push(package.name().to_string());
             url.path_mut().unwrap().push(package.version().to_string());
             url.path_mut().unwrap().push("download".to_string());
+            let _lock = try!(flock::exclusive(&self.config.registry_cache_path(),
+                                              "the package cache", self.config));
             let path = try!(self.download_package(package, &url).chain_error(|| {
                 internal(format!("Failed to download package `{}` from {}",
                                  package, url))
@@ -4,8 +4,9 @@ use std::collections::hash_map::{HashMap};
 use std::env;
 use std::ffi::OsString;
 use std::fmt;
-use std::fs::{self, File};
+use std::fs::{self, File, OpenOptions};
 use std::io::prelude::*;
+use std::io;
 use std::mem;
 use std::path::{Path, PathBuf};
 
@@ -28,6 +29,8 @@ pub struct Config {
     rustc: PathBuf,
     rustdoc: PathBuf,
     target_dir: RefCell<Option<PathBuf>>,
+    deny_yanked: Cell<bool>,
+    locked: Cell<bool>,
 }
 
 impl Config {
@@ -49,6 +52,8 @@ impl Config {
             rustc: PathBuf::from("rustc"),
             rustdoc: PathBuf::from("rustdoc"),
             target_dir: RefCell::new(None),
+            deny_yanked: Cell::new(false),
+            locked: Cell::new(false),
         };
 
         try!(cfg.scrape_tool_config());
@@ -110,6 +115,22 @@ impl Config {
         *self.target_dir.borrow_mut() = Some(path.to_owned());
     }
 
+    /// Whether resolving a graph which contains yanked packages is an error
+    /// rather than a warning.
+    pub fn deny_yanked(&self) -> bool { self.deny_yanked.get() }
+
+    pub fn set_deny_yanked(&self, deny: bool) {
+        self.deny_yanked.set(deny);
+    }
+
+    /// Whether resolution must use the existing lockfile exactly, failing if
+    /// it is missing or would need to be updated.
+    pub fn locked(&self) -> bool { self.locked.get() }
+
+    pub fn set_locked(&self, locked: bool) {
+        self.locked.set(locked);
+    }
+
     pub fn get(&self, key: &str) -> CargoResult<Option<ConfigValue>> {
         let vals = try!(self.values());
         let mut parts = key.split('.').enumerate();
@@ -152,20 +173,52 @@ impl Config {
 
     pub fn get_path(&self, key: &str) -> CargoResult<Option<PathBuf>> {
         if let Some((specified_path, path_to_config)) = try!(self.get_string(&key)) {
-            if specified_path.contains("/") || (cfg!(windows) && specified_path.contains("\\")) {
-                // An absolute or a relative path
-                let prefix_path = path_to_config.parent().unwrap().parent().unwrap();
-                // Joining an absolute path to any path results in the given absolute path
-                Ok(Some(prefix_path.join(specified_path)))
-            } else {
-                // A pathless name
-                Ok(Some(PathBuf::from(specified_path)))
-            }
+            Ok(Some(self.string_to_path(specified_path, &path_to_config)))
         } else {
             Ok(None)
         }
     }
 
+    /// Interprets a path which was specified in the config file at
+    /// `path_to_config`, where relative paths are relative to the directory
+    /// containing the `.cargo` directory.
+    pub fn string_to_path(&self, specified_path: String, path_to_config: &Path)
+                          -> PathBuf {
+        if specified_path.contains("/") || (cfg!(windows) && specified_path.contains("\\")) {
+            // An absolute or a relative path
+            let prefix_path = path_to_config.parent().unwrap().parent().unwrap();
+            // Joining an absolute path to any path results in the given absolute path
+            prefix_path.join(specified_path)
+        } else {
+            // A pathless name
+            PathBuf::from(specified_path)
+        }
+    }
+
+    /// Reads a program to run along with its arguments, which is either a
+    /// string with the program and its arguments separated by spaces, or a
+    /// list of the program followed by its arguments.
+    pub fn get_program(&self, key: &str)
+                       -> CargoResult<Option<(PathBuf, Vec<String>)>> {
+        let (mut args, path) = match try!(self.get(key)) {
+            Some(CV::String(s, path)) => {
+                (s.split_whitespace().map(|s| s.to_string()).collect::<Vec<_>>(),
+                 path)
+            }
+            Some(CV::List(list, path)) => {
+                (list.into_iter().map(|s| s.0).collect(), path)
+            }
+            Some(val) => return self.expected("string or list", key, val),
+            None => return Ok(None),
+        };
+        if args.is_empty() {
+            return Err(human(format!("`{}` in {} must name a program to run",
+                                     key, path.display())))
+        }
+        let program = self.string_to_path(args.remove(0), &path);
+        Ok(Some((program, args)))
+    }
+
     pub fn get_list(&self, key: &str) -> CargoResult<Option<(Vec<(String, PathBuf)>, PathBuf)>> {
         match try!(self.get(key)) {
             Some(CV::List(i, path)) => Ok(Some((i, path))),
@@ -183,6 +236,14 @@ impl Config {
         }
     }
 
+    pub fn get_bool(&self, key: &str) -> CargoResult<Option<(bool, PathBuf)>> {
+        match try!(self.get(key)) {
+            Some(CV::Boolean(b, path)) => Ok(Some((b, path))),
+            Some(val) => self.expected("bool", key, val),
+            None => Ok(None),
+        }
+    }
+
     pub fn get_i64(&self, key: &str) -> CargoResult<Option<(i64, PathBuf)>> {
         match try!(self.get(key)) {
             Some(CV::Integer(i, path)) => Ok(Some((i, path))),
@@ -216,6 +277,21 @@ impl Config {
             Ok(())
         }).chain_error(|| human("Couldn't load Cargo configuration")));
 
+        // Registry tokens are kept in a separate credentials file in the home
+        // directory, which takes precedence over all configuration files.
+        let credentials = self.home_path.join("credentials");
+        if fs::metadata(&credentials).is_ok() {
+            let contents = try!(paths::read(&credentials));
+            let table = try!(cargo_toml::parse(&contents, &credentials));
+            let mut value = try!(CV::from_toml(&credentials,
+                                               toml::Value::Table(table))
+                                    .chain_error(|| {
+                human(format!("failed to load credentials from `{}`",
+                              credentials.display()))
+            }));
+            try!(value.merge(cfg));
+            cfg = value;
+        }
 
         *self.values.borrow_mut() = match cfg {
             CV::Table(map, _) => map,
@@ -521,3 +597,59 @@ pub fn set_config(cfg: &Config, loc: Location, key: &str,
     try!(paths::write(&file, contents.as_bytes()));
     Ok(())
 }
+
+/// Saves the API token for the registry in the `credentials` file of Cargo's
+/// home directory, which is only readable by the current user.
+pub fn save_credentials(cfg: &Config, token: String) -> CargoResult<()> {
+    let file = cfg.home_path.join("credentials");
+    try!(fs::create_dir_all(&cfg.home_path));
+    let contents = paths::read(&file).unwrap_or(String::new());
+    let mut toml = try!(cargo_toml::parse(&contents, &file));
+    let mut registry = match toml.remove("registry") {
+        Some(toml::Value::Table(table)) => table,
+        _ => toml::Table::new(),
+    };
+    registry.insert("token".to_string(), toml::Value::String(token));
+    toml.insert("registry".to_string(), toml::Value::Table(registry));
+    let contents = toml::Value::Table(toml).to_string();
+
+    try!((|| -> CargoResult<()> {
+        let mut f = try!(OpenOptions::new().write(true).create(true)
+                                           .truncate(true).open(&file));
+        // Restrict the permissions before the token is written out.
+        try!(restrict_permissions(&file));
+        try!(f.write_all(contents.as_bytes()));
+        Ok(())
+    })().chain_error(|| {
+        human(format!("failed to write credentials to `{}`", file.display()))
+    }));
+
+    // Older versions of Cargo saved the token in the global configuration,
+    // don't leave a copy of it behind there.
+    let config = cfg.home_path.join("config");
+    if let Ok(contents) = paths::read(&config) {
+        let mut toml = try!(cargo_toml::parse(&contents, &config));
+        let removed = match toml.get_mut("registry") {
+            Some(&mut toml::Value::Table(ref mut registry)) => {
+                registry.remove("token").is_some()
+            }
+            _ => false,
+        };
+        if removed {
+            let contents = toml::Value::Table(toml).to_string();
+            try!(paths::write(&config, contents.as_bytes()));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn restrict_permissions(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+}
+
+#[cfg(not(unix))]
+fn restrict_permissions(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
@@ -0,0 +1,213 @@
+//! Advisory file locks which keep concurrent Cargo processes from stepping on
+//! each other's toes.
+//!
+//! Directories shared between processes (a build directory, the registry
+//! index, the download caches, ...) are guarded by a lock file. Operations
+//! which modify a directory take an exclusive lock on it, while operations
+//! which only read it take a shared lock. A process which has to wait for a
+//! lock says so before blocking.
+//!
+//! The global caches have a second lock file, which builds hold a shared lock
+//! on for as long as they use the sources in them, and which is only locked
+//! exclusively to remove sources from the caches.
+
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use util::{CargoResult, ChainError, Config, human};
+
+use self::imp::{lock_shared, lock_exclusive, try_lock_shared};
+use self::imp::{try_lock_exclusive, unlock, is_contended};
+
+/// The name of the lock file guarding a directory.
+pub const LOCK_FILE: &'static str = ".cargo-lock";
+
+/// The name of the lock file marking the sources in a cache as in use.
+pub const USE_LOCK_FILE: &'static str = ".cargo-use-lock";
+
+/// A lock held on a file, which is released when this is dropped.
+pub struct FileLock {
+    file: File,
+    path: PathBuf,
+}
+
+impl FileLock {
+    pub fn path(&self) -> &Path { &self.path }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = unlock(&self.file);
+    }
+}
+
+/// Takes a shared lock on the directory `dir`, blocking while another process
+/// holds an exclusive lock on it. `what` describes the directory to the user.
+pub fn shared(dir: &Path, what: &str, config: &Config)
+              -> CargoResult<FileLock> {
+    acquire(dir, LOCK_FILE, what, config, try_lock_shared, lock_shared)
+}
+
+/// Takes an exclusive lock on the directory `dir`, blocking while any other
+/// process holds a lock on it. `what` describes the directory to the user.
+pub fn exclusive(dir: &Path, what: &str, config: &Config)
+                 -> CargoResult<FileLock> {
+    acquire(dir, LOCK_FILE, what, config, try_lock_exclusive, lock_exclusive)
+}
+
+/// Marks the sources in the cache `dir` as in use, keeping them from being
+/// removed until the lock is dropped.
+///
+/// This has to be taken before any of the other locks on `dir`, as the lock
+/// file is a different one.
+pub fn in_use(dir: &Path, what: &str, config: &Config)
+              -> CargoResult<FileLock> {
+    acquire(dir, USE_LOCK_FILE, what, config, try_lock_shared, lock_shared)
+}
+
+/// Waits until no build uses the sources in the cache `dir`, and keeps any
+/// from starting to use them until the lock is dropped.
+pub fn unused(dir: &Path, what: &str, config: &Config)
+              -> CargoResult<FileLock> {
+    acquire(dir, USE_LOCK_FILE, what, config, try_lock_exclusive,
+            lock_exclusive)
+}
+
+fn acquire(dir: &Path, name: &str, what: &str, config: &Config,
+           try_lock: fn(&File) -> io::Result<()>,
+           lock: fn(&File) -> io::Result<()>) -> CargoResult<FileLock> {
+    let path = dir.join(name);
+    let file = try!((|| -> io::Result<File> {
+        try!(fs::create_dir_all(dir));
+        OpenOptions::new().read(true).write(true).create(true).open(&path)
+    })().chain_error(|| {
+        human(format!("failed to open the lock file `{}`", path.display()))
+    }));
+
+    match try_lock(&file) {
+        Ok(()) => return Ok(FileLock { file: file, path: path }),
+        Err(ref e) if is_contended(e) => {}
+        Err(e) => {
+            return Err(e).chain_error(|| {
+                human(format!("failed to lock `{}`", path.display()))
+            })
+        }
+    }
+
+    try!(config.shell().status("Blocking",
+                               format!("waiting for file lock on {}", what)));
+    try!(lock(&file).chain_error(|| {
+        human(format!("failed to lock `{}`", path.display()))
+    }));
+    Ok(FileLock { file: file, path: path })
+}
+
+#[cfg(unix)]
+mod imp {
+    use std::fs::File;
+    use std::io;
+    use std::os::unix::prelude::*;
+
+    use libc::c_int;
+
+    const LOCK_SH: c_int = 1;
+    const LOCK_EX: c_int = 2;
+    const LOCK_NB: c_int = 4;
+    const LOCK_UN: c_int = 8;
+
+    extern {
+        fn flock(fd: c_int, operation: c_int) -> c_int;
+    }
+
+    fn call(file: &File, operation: c_int) -> io::Result<()> {
+        if unsafe { flock(file.as_raw_fd(), operation) } == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+
+    pub fn lock_shared(file: &File) -> io::Result<()> {
+        call(file, LOCK_SH)
+    }
+
+    pub fn lock_exclusive(file: &File) -> io::Result<()> {
+        call(file, LOCK_EX)
+    }
+
+    pub fn try_lock_shared(file: &File) -> io::Result<()> {
+        call(file, LOCK_SH | LOCK_NB)
+    }
+
+    pub fn try_lock_exclusive(file: &File) -> io::Result<()> {
+        call(file, LOCK_EX | LOCK_NB)
+    }
+
+    pub fn unlock(file: &File) -> io::Result<()> {
+        call(file, LOCK_UN)
+    }
+
+    pub fn is_contended(err: &io::Error) -> bool {
+        err.kind() == io::ErrorKind::WouldBlock
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    extern crate kernel32;
+    extern crate winapi;
+
+    use std::fs::File;
+    use std::io;
+    use std::mem;
+    use std::os::windows::prelude::*;
+
+    use self::winapi::{DWORD, HANDLE, ERROR_LOCK_VIOLATION};
+    use self::winapi::{LOCKFILE_EXCLUSIVE_LOCK, LOCKFILE_FAIL_IMMEDIATELY};
+
+    fn lock(file: &File, flags: DWORD) -> io::Result<()> {
+        unsafe {
+            let mut overlapped = mem::zeroed();
+            let ret = kernel32::LockFileEx(file.as_raw_handle() as HANDLE,
+                                           flags, 0, !0, !0, &mut overlapped);
+            if ret == 0 {
+                Err(io::Error::last_os_error())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    pub fn lock_shared(file: &File) -> io::Result<()> {
+        lock(file, 0)
+    }
+
+    pub fn lock_exclusive(file: &File) -> io::Result<()> {
+        lock(file, LOCKFILE_EXCLUSIVE_LOCK)
+    }
+
+    pub fn try_lock_shared(file: &File) -> io::Result<()> {
+        lock(file, LOCKFILE_FAIL_IMMEDIATELY)
+    }
+
+    pub fn try_lock_exclusive(file: &File) -> io::Result<()> {
+        lock(file, LOCKFILE_EXCLUSIVE_LOCK | LOCKFILE_FAIL_IMMEDIATELY)
+    }
+
+    pub fn unlock(file: &File) -> io::Result<()> {
+        unsafe {
+            let ret = kernel32::UnlockFile(file.as_raw_handle() as HANDLE,
+                                           0, 0, !0, !0);
+            if ret == 0 {
+                Err(io::Error::last_os_error())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    pub fn is_contended(err: &io::Error) -> bool {
+        err.raw_os_error() == Some(ERROR_LOCK_VIOLATION as i32)
+    }
+}
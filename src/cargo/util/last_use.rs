@@ -0,0 +1,56 @@
+//! Tracking of when the entries of the caches in `CARGO_HOME` were last used.
+//!
+//! Each downloaded crate, unpacked source, git database and git checkout has a
+//! marker file in a `.last-use` directory next to it, and the modification
+//! time of the marker is the last time a build used the entry. These times are
+//! what `cargo cache gc` goes by when deciding what to remove.
+
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use filetime::{self, FileTime};
+use time;
+
+use util::{CargoResult, ChainError, internal};
+
+/// The name of the directory holding the markers of the entries next to it.
+pub const MARKER_DIR: &'static str = ".last-use";
+
+/// Returns the path of the marker recording when `entry` was last used.
+pub fn marker(entry: &Path) -> PathBuf {
+    let name = entry.file_name().unwrap();
+    entry.parent().unwrap().join(MARKER_DIR).join(name)
+}
+
+/// Records that `entry` is being used right now.
+///
+/// This is done before an entry is created as well, so an entry which is
+/// still being downloaded or checked out always looks recently used.
+pub fn record(entry: &Path) -> CargoResult<()> {
+    let marker = marker(entry);
+    (|| -> CargoResult<()> {
+        try!(fs::create_dir_all(marker.parent().unwrap()));
+        if fs::metadata(&marker).is_err() {
+            try!(File::create(&marker));
+        }
+        let now = time::get_time();
+        let now = FileTime::from_seconds_since_1970(now.sec as u64,
+                                                    now.nsec as u32);
+        try!(filetime::set_file_times(&marker, now, now));
+        Ok(())
+    }).chain_error(|| {
+        internal(format!("failed to record the use of `{}`", entry.display()))
+    })
+}
+
+/// Returns when `entry` was last used, in seconds since the epoch.
+///
+/// Entries created by versions of Cargo which didn't record their use fall
+/// back to their own modification time.
+pub fn last_use(entry: &Path) -> CargoResult<u64> {
+    let meta = match fs::metadata(&marker(entry)) {
+        Ok(meta) => meta,
+        Err(..) => try!(fs::metadata(entry)),
+    };
+    Ok(FileTime::from_last_modification_time(&meta).seconds_relative_to_1970())
+}
@@ -19,9 +19,11 @@ pub use self::vcs::{GitRepo, HgRepo};
 
 pub mod config;
 pub mod errors;
+pub mod flock;
 pub mod graph;
 pub mod hex;
 pub mod important_paths;
+pub mod last_use;
 pub mod paths;
 pub mod process_builder;
 pub mod profile;
@@ -1,6 +1,6 @@
 use std::env;
 use std::ffi::{OsStr, OsString};
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::prelude::*;
 use std::path::{Path, PathBuf, Component};
 
@@ -87,6 +87,21 @@ pub fn write(path: &Path, contents: &[u8]) -> CargoResult<()> {
     })
 }
 
+/// Returns the number of files below `path` and their total size in bytes.
+pub fn disk_usage(path: &Path) -> CargoResult<(u64, u64)> {
+    let metadata = try!(fs::symlink_metadata(path));
+    if !metadata.is_dir() {
+        return Ok((1, metadata.len()))
+    }
+    let mut total = (0, 0);
+    for entry in try!(fs::read_dir(path)) {
+        let (files, bytes) = try!(disk_usage(&try!(entry).path()));
+        total.0 += files;
+        total.1 += bytes;
+    }
+    Ok(total)
+}
+
 #[cfg(unix)]
 pub fn path2bytes(path: &Path) -> CargoResult<&[u8]> {
     use std::os::unix::prelude::*;
@@ -2,8 +2,10 @@ use std::collections::HashMap;
 use std::env;
 use std::ffi::{OsString, OsStr};
 use std::fmt;
+use std::io::prelude::*;
+use std::io::{self, BufReader};
 use std::path::Path;
-use std::process::{Command, Output};
+use std::process::{Command, ExitStatus, Output, Stdio};
 
 use util::{CargoResult, ProcessError, process_error};
 use util::shell_escape::escape;
@@ -71,37 +73,84 @@ impl ProcessBuilder {
 
     pub fn exec(&self) -> Result<(), ProcessError> {
         let mut command = self.build_command();
-        let exit = try!(command.status().map_err(|e| {
-            process_error(&format!("Could not execute process `{}`",
-                                   self.debug_string()),
-                          Some(e), None, None)
-        }));
+        let exit = try!(command.status().map_err(|e| self.spawn_error(e)));
 
         if exit.success() {
             Ok(())
         } else {
-            Err(process_error(&format!("Process didn't exit successfully: `{}`",
-                                       self.debug_string()),
-                              None, Some(&exit), None))
+            Err(self.exit_error(&exit, None))
         }
     }
 
     pub fn exec_with_output(&self) -> Result<Output, ProcessError> {
         let mut command = self.build_command();
 
-        let output = try!(command.output().map_err(|e| {
-            process_error(&format!("Could not execute process `{}`",
-                               self.debug_string()),
-                          Some(e), None, None)
-        }));
+        let output = try!(command.output().map_err(|e| self.spawn_error(e)));
 
         if output.status.success() {
             Ok(output)
         } else {
-            Err(process_error(&format!("Process didn't exit successfully: `{}`",
-                                       self.debug_string()),
-                              None, Some(&output.status), Some(&output)))
+            Err(self.exit_error(&output.status, Some(&output)))
+        }
+    }
+
+    /// Executes the process, handing each line it prints to stdout to
+    /// `on_line` as soon as it's printed. Stderr is inherited as usual.
+    pub fn exec_with_streaming(&self, on_line: &mut FnMut(&str))
+                               -> Result<(), ProcessError> {
+        let mut command = self.build_command();
+        command.stdout(Stdio::piped());
+        let mut child = try!(command.spawn().map_err(|e| self.spawn_error(e)));
+
+        {
+            // Output which isn't valid UTF-8 is passed on lossily rather than
+            // ending the reading, as a child whose output isn't read any more
+            // blocks once the pipe is full. If reading fails nonetheless the
+            // pipe is closed before waiting for the child.
+            let mut stdout = BufReader::new(child.stdout.take().unwrap());
+            let mut line = Vec::new();
+            loop {
+                line.clear();
+                match stdout.read_until(b'\n', &mut line) {
+                    Ok(0) => break,
+                    Ok(..) => {}
+                    Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {
+                        continue
+                    }
+                    Err(..) => break,
+                }
+                if line.last() == Some(&b'\n') {
+                    line.pop();
+                    if line.last() == Some(&b'\r') {
+                        line.pop();
+                    }
+                }
+                on_line(&String::from_utf8_lossy(&line));
+            }
         }
+
+        let exit = try!(child.wait().map_err(|e| self.spawn_error(e)));
+        if exit.success() {
+            Ok(())
+        } else {
+            Err(self.exit_error(&exit, None))
+        }
+    }
+
+    /// The error for when the process couldn't be executed.
+    pub fn spawn_error(&self, error: io::Error) -> ProcessError {
+        process_error(&format!("Could not execute process `{}`",
+                               self.debug_string()),
+                      Some(error), None, None)
+    }
+
+    /// The error for when the process exited unsuccessfully, including its
+    /// output if it was captured.
+    pub fn exit_error(&self, status: &ExitStatus, output: Option<&Output>)
+                      -> ProcessError {
+        process_error(&format!("Process didn't exit successfully: `{}`",
+                               self.debug_string()),
+                      None, Some(status), output)
     }
 
     pub fn build_command(&self) -> Command {
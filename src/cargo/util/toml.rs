@@ -1,8 +1,8 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::default::Default;
 use std::fmt;
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 use std::str;
 
 use toml;
@@ -179,6 +179,149 @@ pub fn parse(toml: &str, file: &Path) -> CargoResult<toml::Table> {
     Err(human(error_str))
 }
 
+/// Generate the manifest which is placed in a packaged `.crate` file.
+///
+/// The original manifest at `file` is rewritten so it is self-contained:
+/// path dependencies become registry dependencies and every target of
+/// `manifest` is listed explicitly with a path relative to `root`. Targets
+/// whose source file isn't among the `packaged` files are left out, as they
+/// couldn't be built from the package. Everything else in the original
+/// manifest is carried over as-is.
+pub fn to_packaged_manifest(contents: &str,
+                            file: &Path,
+                            manifest: &Manifest,
+                            root: &Path,
+                            packaged: &[PathBuf]) -> CargoResult<String> {
+    const DEP_KEYS: [&'static str; 5] = ["dependencies",
+                                         "dev-dependencies", "dev_dependencies",
+                                         "build-dependencies", "build_dependencies"];
+
+    let mut toml = try!(parse(contents, file));
+
+    for key in DEP_KEYS.iter() {
+        if let Some(&mut toml::Value::Table(ref mut deps)) = toml.get_mut(*key) {
+            strip_paths(deps);
+        }
+    }
+    if let Some(&mut toml::Value::Table(ref mut platforms)) = toml.get_mut("target") {
+        for (_, platform) in platforms.iter_mut() {
+            let platform = match *platform {
+                toml::Value::Table(ref mut t) => t,
+                _ => continue,
+            };
+            for key in DEP_KEYS.iter() {
+                if let Some(&mut toml::Value::Table(ref mut deps)) =
+                        platform.get_mut(*key) {
+                    strip_paths(deps);
+                }
+            }
+        }
+    }
+
+    let is_packaged = |target: &Target| {
+        packaged.iter().any(|p| *p == root.join(target.src_path()))
+    };
+    if let Some(lib) = manifest.targets().iter().find(|t| t.is_lib()) {
+        // The library name is left implicit as inferred names may contain
+        // hyphens, which are rejected when written out explicitly.
+        let mut table = match toml.remove("lib") {
+            Some(toml::Value::Table(t)) => t,
+            _ => toml::Table::new(),
+        };
+        if is_packaged(lib) {
+            table.insert("path".to_string(),
+                         toml::Value::String(try!(relative_path(lib, root))));
+            toml.insert("lib".to_string(), toml::Value::Table(table));
+        }
+    }
+    try!(explicit_targets(&mut toml, "bin", manifest, root,
+                          |t| t.is_bin() && is_packaged(t)));
+    try!(explicit_targets(&mut toml, "example", manifest, root,
+                          |t| t.is_example() && is_packaged(t)));
+    try!(explicit_targets(&mut toml, "test", manifest, root,
+                          |t| t.is_test() && is_packaged(t)));
+    try!(explicit_targets(&mut toml, "bench", manifest, root,
+                          |t| t.is_bench() && is_packaged(t)));
+
+    return Ok(format!("\
+# This file is automatically generated by `cargo package`.
+#
+# Path dependencies have been replaced with registry dependencies and all
+# packaged targets are listed explicitly. The original manifest can be found
+# in `Cargo.toml.orig`.
+
+{}", toml::Value::Table(toml)));
+
+    // Path dependencies are always published as dependencies on the
+    // registry, so only the version requirement is retained.
+    fn strip_paths(deps: &mut toml::Table) {
+        for (_, dep) in deps.iter_mut() {
+            let dep = match *dep {
+                toml::Value::Table(ref mut t) => t,
+                _ => continue,
+            };
+            if dep.remove("path").is_some() && !dep.contains_key("version") {
+                dep.insert("version".to_string(),
+                           toml::Value::String("*".to_string()));
+            }
+        }
+    }
+
+    fn explicit_targets<F>(toml: &mut toml::Table, key: &str,
+                           manifest: &Manifest, root: &Path, f: F)
+                           -> CargoResult<()>
+        where F: Fn(&Target) -> bool
+    {
+        let mut existing = match toml.remove(key) {
+            Some(toml::Value::Array(a)) => a,
+            _ => Vec::new(),
+        };
+        let mut ret = Vec::new();
+        for target in manifest.targets().iter().filter(|t| f(t)) {
+            let pos = existing.iter().position(|t| {
+                t.lookup("name").and_then(|n| n.as_str()) == Some(target.name())
+            });
+            let mut table = match pos.map(|i| existing.remove(i)) {
+                Some(toml::Value::Table(t)) => t,
+                _ => toml::Table::new(),
+            };
+            table.insert("name".to_string(),
+                         toml::Value::String(target.name().to_string()));
+            table.insert("path".to_string(),
+                         toml::Value::String(try!(relative_path(target, root))));
+            ret.push(toml::Value::Table(table));
+        }
+        if !ret.is_empty() {
+            toml.insert(key.to_string(), toml::Value::Array(ret));
+        }
+        Ok(())
+    }
+
+    #[allow(deprecated)] // connect => join in 1.3
+    fn relative_path(target: &Target, root: &Path) -> CargoResult<String> {
+        let path = target.src_path();
+        let relative = if path.is_absolute() {
+            util::without_prefix(path, root)
+        } else {
+            Some(path)
+        };
+        let parts = relative.and_then(|p| {
+            p.components().map(|c| {
+                match c {
+                    Component::Normal(s) => s.to_str(),
+                    _ => None,
+                }
+            }).collect::<Option<Vec<_>>>()
+        });
+        match parts {
+            Some(parts) => Ok(parts.connect("/")),
+            None => Err(human(format!("the source of target `{}` is not a \
+                                       path inside of the package: {}",
+                                      target.name(), path.display()))),
+        }
+    }
+}
+
 type TomlLibTarget = TomlTarget;
 type TomlBinTarget = TomlTarget;
 type TomlExampleTarget = TomlTarget;
@@ -224,6 +367,7 @@ pub struct TomlManifest {
     build_dependencies: Option<HashMap<String, TomlDependency>>,
     features: Option<HashMap<String, Vec<String>>>,
     target: Option<HashMap<String, TomlPlatform>>,
+    badges: Option<BTreeMap<String, BTreeMap<String, String>>>,
 }
 
 #[derive(RustcDecodable, Clone, Default)]
@@ -261,6 +405,7 @@ pub struct TomlProject {
     documentation: Option<String>,
     readme: Option<String>,
     keywords: Option<Vec<String>>,
+    categories: Option<Vec<String>>,
     license: Option<String>,
     license_file: Option<String>,
     repository: Option<String>,
@@ -540,6 +685,8 @@ impl TomlManifest {
             license_file: project.license_file.clone(),
             repository: project.repository.clone(),
             keywords: project.keywords.clone().unwrap_or(Vec::new()),
+            categories: project.categories.clone().unwrap_or(Vec::new()),
+            badges: self.badges.clone().unwrap_or(BTreeMap::new()),
         };
         let profiles = build_profiles(&self.profile);
         let mut manifest = Manifest::new(summary,
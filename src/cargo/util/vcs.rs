@@ -1,8 +1,9 @@
-use std::path::Path;
+use std::fs;
+use std::path::{Path, PathBuf};
 
 use git2;
 
-use util::{CargoResult, process};
+use util::{CargoResult, human, process};
 
 pub struct HgRepo;
 pub struct GitRepo;
@@ -26,5 +27,21 @@ impl HgRepo {
         try!(try!(process("hg")).arg("root").cwd(path).exec_with_output());
         return Ok(HgRepo)
     }
+    /// The root of the repository `path` is in, which is the closest of its
+    /// ancestors containing a `.hg` directory. Unlike the output of `hg
+    /// root`, it's always a prefix of `path`.
+    pub fn root(path: &Path) -> CargoResult<PathBuf> {
+        let mut dir = path;
+        loop {
+            if fs::metadata(&dir.join(".hg")).is_ok() {
+                return Ok(dir.to_path_buf())
+            }
+            dir = match dir.parent() {
+                Some(parent) => parent,
+                None => return Err(human(format!("`{}` is not in a Mercurial \
+                                                  repository", path.display()))),
+            };
+        }
+    }
 }
 
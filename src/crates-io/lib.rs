@@ -1,13 +1,14 @@
 extern crate curl;
 extern crate rustc_serialize;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
 use std::fs::{self, File};
 use std::io::prelude::*;
 use std::io::{self, Cursor};
 use std::path::Path;
 use std::result;
+use std::str;
 
 use curl::http;
 use curl::http::handle::Method::{Put, Get, Delete};
@@ -32,7 +33,8 @@ pub enum Error {
     Curl(curl::ErrCode),
     NotOkResponse(http::Response),
     NonUtf8Body,
-    Api(Vec<String>),
+    /// The registry rejected the request, describing why in the response.
+    Api(Vec<ApiError>),
     Unauthorized,
     TokenMissing,
     Io(io::Error),
@@ -61,6 +63,9 @@ pub struct NewCrate {
     pub license: Option<String>,
     pub license_file: Option<String>,
     pub repository: Option<String>,
+    pub categories: Vec<String>,
+    pub badges: BTreeMap<String, BTreeMap<String, String>>,
+    pub links: Option<String>,
 }
 
 #[derive(RustcEncodable)]
@@ -78,14 +83,21 @@ pub struct NewCrateDependency {
 pub struct User {
     pub id: u32,
     pub login: String,
+    /// Either `user` or `team`; older registries don't report this.
+    pub kind: Option<String>,
     pub avatar: Option<String>,
     pub email: Option<String>,
     pub name: Option<String>,
 }
 
+/// A single error reported by the registry's API.
+#[derive(RustcDecodable, Clone, Debug)]
+pub struct ApiError {
+    pub detail: String,
+}
+
 #[derive(RustcDecodable)] struct R { ok: bool }
 #[derive(RustcDecodable)] struct ApiErrorList { errors: Vec<ApiError> }
-#[derive(RustcDecodable)] struct ApiError { detail: String }
 #[derive(RustcEncodable)] struct OwnersReq<'a> { users: &'a [&'a str] }
 #[derive(RustcDecodable)] struct Users { users: Vec<User> }
 #[derive(RustcDecodable)] struct Crates { crates: Vec<Crate> }
@@ -104,6 +116,8 @@ impl Registry {
         }
     }
 
+    /// Owners are either the login of a user or a team of the form
+    /// `github:org:team`.
     pub fn add_owners(&mut self, krate: &str, owners: &[&str]) -> Result<()> {
         let body = json::encode(&OwnersReq { users: owners }).unwrap();
         let body = try!(self.put(format!("/crates/{}/owners", krate),
@@ -233,21 +247,31 @@ fn handle(response: result::Result<http::Response, curl::ErrCode>)
         200 => {}
         403 => return Err(Error::Unauthorized),
         404 => return Err(Error::NotFound),
-        _ => return Err(Error::NotOkResponse(response))
+        _ => {
+            // Failed requests usually explain themselves in the body, which
+            // is more helpful than the status code alone.
+            return match api_errors(response.get_body()) {
+                Some(errors) => Err(Error::Api(errors)),
+                None => Err(Error::NotOkResponse(response)),
+            }
+        }
+    }
+
+    if let Some(errors) = api_errors(response.get_body()) {
+        return Err(Error::Api(errors))
+    }
+    match String::from_utf8(response.move_body()) {
+        Ok(body) => Ok(body),
+        Err(..) => Err(Error::NonUtf8Body),
     }
+}
 
-    let body = match String::from_utf8(response.move_body()) {
+fn api_errors(body: &[u8]) -> Option<Vec<ApiError>> {
+    let body = match str::from_utf8(body) {
         Ok(body) => body,
-        Err(..) => return Err(Error::NonUtf8Body),
+        Err(..) => return None,
     };
-    match json::decode::<ApiErrorList>(&body) {
-        Ok(errors) => {
-            return Err(Error::Api(errors.errors.into_iter().map(|s| s.detail)
-                                        .collect()))
-        }
-        Err(..) => {}
-    }
-    Ok(body)
+    json::decode::<ApiErrorList>(body).ok().map(|list| list.errors)
 }
 
 impl fmt::Display for Error {
@@ -260,7 +284,9 @@ impl fmt::Display for Error {
                 write!(f, "failed to get a 200 OK response: {}", resp)
             }
             Error::Api(ref errs) => {
-                write!(f, "api errors: {}", errs.connect(", "))
+                let details = errs.iter().map(|e| &e.detail[..])
+                                  .collect::<Vec<_>>();
+                write!(f, "api errors: {}", details.connect(", "))
             }
             Error::Unauthorized => write!(f, "unauthorized API access"),
             Error::TokenMissing => write!(f, "no upload token found, please run `cargo login`"),
@@ -37,6 +37,13 @@ pub fn init() {
 }
 
 pub fn mock_archive(name: &str, version: &str, deps: &[(&str, &str, &str)]) {
+    mock_archive_files(name, version, deps, &[])
+}
+
+/// Like `mock_archive`, also packaging each `(path, contents)` of `files`.
+pub fn mock_archive_files(name: &str, version: &str,
+                          deps: &[(&str, &str, &str)],
+                          files: &[(&str, &str)]) {
     let mut manifest = format!(r#"
         [package]
         name = "{}"
@@ -53,13 +60,16 @@ pub fn mock_archive(name: &str, version: &str, deps: &[(&str, &str, &str)]) {
             _ => ""
         }, dep, req));
     }
-    let p = project(name)
+    let mut p = project(name)
         .file("Cargo.toml", &manifest)
         .file("src/lib.rs", "")
         .file("src/main.rs", &format!("\
             extern crate {};
             fn main() {{}}
         ", name));
+    for &(path, contents) in files.iter() {
+        p = p.file(path, contents);
+    }
     p.build();
 
     let dst = mock_archive_dst(name, version);
@@ -72,6 +82,10 @@ pub fn mock_archive(name: &str, version: &str, deps: &[(&str, &str, &str)]) {
                   &mut File::open(&p.root().join("src/lib.rs")).unwrap()).unwrap();
     a.append_file(&format!("{}-{}/src/main.rs", name, version),
                   &mut File::open(&p.root().join("src/main.rs")).unwrap()).unwrap();
+    for &(path, _) in files.iter() {
+        a.append_file(&format!("{}-{}/{}", name, version, path),
+                      &mut File::open(&p.root().join(path)).unwrap()).unwrap();
+    }
     a.finish().unwrap();
 }
 
@@ -85,7 +99,13 @@ pub fn mock_pkg(name: &str, version: &str, deps: &[(&str, &str, &str)]) {
 
 pub fn mock_pkg_yank(name: &str, version: &str, deps: &[(&str, &str, &str)],
                      yanked: bool) {
-    mock_archive(name, version, deps);
+    mock_pkg_files(name, version, deps, &[], yanked)
+}
+
+/// Publish a package which also contains each `(path, contents)` of `files`.
+pub fn mock_pkg_files(name: &str, version: &str, deps: &[(&str, &str, &str)],
+                      files: &[(&str, &str)], yanked: bool) {
+    mock_archive_files(name, version, deps, files);
     let mut c = Vec::new();
     File::open(&mock_archive_dst(name, version)).unwrap()
          .read_to_end(&mut c).unwrap();
@@ -0,0 +1,225 @@
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::thread;
+
+use filetime::{self, FileTime};
+
+use support::{project, execs, DOWNLOADING};
+use support::paths;
+use support::registry as r;
+
+use hamcrest::{assert_that, existing_dir, existing_file, is_not};
+
+fn setup() {
+    r::init();
+}
+
+// The directory of the only registry in the `dir` cache of `CARGO_HOME`.
+fn registry_dir(dir: &str) -> PathBuf {
+    let dir = paths::home().join(".cargo/registry").join(dir);
+    fs::read_dir(&dir).unwrap().next().unwrap().unwrap().path()
+}
+
+// Pretends that the entry was last used `days` days ago.
+fn used_days_ago(entry: &Path, days: u64) {
+    let marker = entry.parent().unwrap().join(".last-use")
+                      .join(entry.file_name().unwrap());
+    let meta = fs::metadata(&marker).unwrap();
+    let mtime = FileTime::from_last_modification_time(&meta);
+    let then = mtime.seconds_relative_to_1970() - days * 24 * 60 * 60;
+    let then = FileTime::from_seconds_since_1970(then, 0);
+    filetime::set_file_times(&marker, then, then).unwrap();
+}
+
+fn project_with_dep() -> ::support::ProjectBuilder {
+    r::mock_pkg("bar", "0.0.1", &[]);
+    project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+}
+
+test!(records_last_use {
+    let p = project_with_dep();
+    assert_that(p.cargo_process("build"), execs().with_status(0));
+
+    assert_that(&registry_dir("src").join(".last-use/bar-0.0.1"),
+                existing_file());
+    assert_that(&registry_dir("cache").join(".last-use/bar-0.0.1.crate"),
+                existing_file());
+});
+
+test!(max_age {
+    let p = project_with_dep();
+    assert_that(p.cargo_process("build"), execs().with_status(0));
+
+    let src = registry_dir("src").join("bar-0.0.1");
+    let krate = registry_dir("cache").join("bar-0.0.1.crate");
+    used_days_ago(&src, 3);
+
+    assert_that(p.cargo("cache").arg("gc").arg("--max-age").arg("2"),
+                execs().with_status(0).with_stdout("\
+[..]Removed 1 cache entries totalling [..] bytes
+"));
+    assert_that(&src, is_not(existing_dir()));
+    assert_that(&krate, existing_file());
+
+    // The crate is unpacked again without downloading it
+    assert_that(p.cargo("build"), execs().with_status(0));
+    assert_that(&src, existing_dir());
+
+    used_days_ago(&src, 3);
+    used_days_ago(&krate, 3);
+    assert_that(p.cargo("cache").arg("gc").arg("--max-age").arg("2"),
+                execs().with_status(0).with_stdout("\
+[..]Removed 2 cache entries totalling [..] bytes
+"));
+    assert_that(&src, is_not(existing_dir()));
+    assert_that(&krate, is_not(existing_file()));
+
+    assert_that(p.cargo("build"),
+                execs().with_status(0).with_stdout_contains(&format!("\
+{downloading} bar v0.0.1 (registry file://[..])
+", downloading = DOWNLOADING)));
+});
+
+test!(max_size_keeps_recently_used {
+    let p = project_with_dep();
+    assert_that(p.cargo_process("build"), execs().with_status(0));
+
+    assert_that(p.cargo("cache").arg("gc").arg("--max-size").arg("0"),
+                execs().with_status(0).with_stdout("\
+[..]Removed 0 cache entries totalling 0 bytes
+"));
+    assert_that(&registry_dir("src").join("bar-0.0.1"), existing_dir());
+
+    used_days_ago(&registry_dir("src").join("bar-0.0.1"), 1);
+    assert_that(p.cargo("cache").arg("gc").arg("--max-size").arg("0"),
+                execs().with_status(0).with_stdout("\
+[..]Removed 1 cache entries totalling [..] bytes
+"));
+    assert_that(&registry_dir("src").join("bar-0.0.1"), is_not(existing_dir()));
+});
+
+test!(dry_run {
+    let p = project_with_dep();
+    assert_that(p.cargo_process("build"), execs().with_status(0));
+
+    let src = registry_dir("src").join("bar-0.0.1");
+    used_days_ago(&src, 3);
+
+    assert_that(p.cargo("cache").arg("gc").arg("--max-age").arg("2")
+                 .arg("--dry-run"),
+                execs().with_status(0).with_stdout(&format!("\
+{src}
+would remove 1 cache entries totalling [..] bytes
+", src = src.display())));
+    assert_that(&src, existing_dir());
+});
+
+test!(limit_required {
+    let p = project("foo");
+    assert_that(p.cargo_process("cache").arg("gc"),
+                execs().with_status(101).with_stderr("\
+either a maximum age or a maximum size of the caches must be given
+"));
+});
+
+test!(invalid_size {
+    let p = project("foo");
+    assert_that(p.cargo_process("cache").arg("gc").arg("--max-size").arg("lots"),
+                execs().with_status(1).with_stderr("\
+invalid size `lots`, expected a number of bytes such as `500M` or `2G`
+"));
+});
+
+test!(gc_waits_for_running_build {
+    r::mock_pkg("bar", "0.0.1", &[]);
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            build = "build.rs"
+
+            [dependencies]
+            bar = "0.0.1"
+        "#)
+        .file("build.rs", r#"
+            use std::fs::{self, File};
+            use std::thread;
+
+            fn main() {
+                File::create("started").unwrap();
+                while fs::metadata("go").is_err() {
+                    thread::sleep_ms(100);
+                }
+            }
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    p.build();
+
+    let mut build = p.cargo("build").build_command()
+                     .stdout(Stdio::null()).spawn().unwrap();
+    while fs::metadata(&p.root().join("started")).is_err() {
+        thread::sleep_ms(100);
+    }
+
+    // The running build uses the sources of `bar`, so they can't be removed
+    // until it has finished, however long ago they were last used.
+    let src = registry_dir("src").join("bar-0.0.1");
+    used_days_ago(&src, 3);
+    let mut gc = p.cargo("cache").arg("gc").arg("--max-age").arg("2")
+                  .build_command().stdout(Stdio::piped()).spawn().unwrap();
+    let mut stdout = BufReader::new(gc.stdout.take().unwrap());
+    let mut line = String::new();
+    stdout.read_line(&mut line).unwrap();
+    assert!(line.contains("Blocking waiting for file lock on the package cache"),
+            "unexpected output: {}", line);
+    assert_that(&src, existing_dir());
+
+    File::create(&p.root().join("go")).unwrap();
+    assert!(build.wait().unwrap().success());
+    assert!(gc.wait().unwrap().success());
+    assert_that(&src, is_not(existing_dir()));
+});
+
+test!(max_age_install_target_dir {
+    r::mock_pkg("bar", "0.0.1", &[]);
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    p.build();
+    assert_that(p.cargo("install").arg("bar"), execs().with_status(0));
+
+    let dir = paths::home().join(".cargo/target-install");
+    assert_that(&dir.join("release"), existing_dir());
+    used_days_ago(&dir, 3);
+
+    assert_that(p.cargo("cache").arg("gc").arg("--max-age").arg("2"),
+                execs().with_status(0).with_stdout("\
+[..]Removed 1 cache entries totalling [..] bytes
+"));
+    assert_that(&dir.join("release"), is_not(existing_dir()));
+
+    // Installing builds in it again.
+    assert_that(p.cargo("install").arg("bar").arg("--force"),
+                execs().with_status(0));
+    assert_that(&dir.join("release"), existing_dir());
+});
@@ -1,4 +1,8 @@
 use std::env;
+use std::fs;
+use std::path::Path;
+
+use cargo::util::paths;
 
 use support::{project, execs, main_file, basic_bin_manifest};
 use hamcrest::{assert_that, existing_dir, existing_file, is_not};
@@ -93,3 +97,111 @@ test!(clean_multiple_packages {
     assert_that(d1_path, is_not(existing_file()));
     assert_that(d2_path, is_not(existing_file()));
 });
+
+test!(clean_release {
+    let p = project("foo")
+              .file("Cargo.toml", &basic_bin_manifest("foo"))
+              .file("src/foo.rs", &main_file(r#""i am foo""#, &[]));
+
+    assert_that(p.cargo_process("build"), execs().with_status(0));
+    assert_that(p.cargo("build").arg("--release"), execs().with_status(0));
+
+    assert_that(p.cargo("clean").arg("--release"),
+                execs().with_status(0).with_stdout(""));
+    assert_that(&p.build_dir().join("release"), is_not(existing_dir()));
+    assert_that(&p.build_dir().join("debug"), existing_dir());
+});
+
+test!(clean_doc {
+    let p = project("foo")
+              .file("Cargo.toml", &basic_bin_manifest("foo"))
+              .file("src/foo.rs", &main_file(r#""i am foo""#, &[]));
+
+    assert_that(p.cargo_process("build"), execs().with_status(0));
+    assert_that(p.cargo("doc"), execs().with_status(0));
+    assert_that(&p.build_dir().join("doc"), existing_dir());
+
+    assert_that(p.cargo("clean").arg("--doc"),
+                execs().with_status(0).with_stdout(""));
+    assert_that(&p.build_dir().join("doc"), is_not(existing_dir()));
+    assert_that(&p.bin("foo"), existing_file());
+});
+
+test!(clean_dry_run {
+    let p = project("foo")
+              .file("Cargo.toml", &basic_bin_manifest("foo"))
+              .file("src/foo.rs", &main_file(r#""i am foo""#, &[]));
+
+    assert_that(p.cargo_process("build"), execs().with_status(0));
+
+    assert_that(p.cargo("clean").arg("--dry-run"),
+                execs().with_status(0).with_stdout(&format!("\
+{dir}
+would remove [..] files totalling [..] bytes
+", dir = p.build_dir().display())));
+    assert_that(&p.bin("foo"), existing_file());
+});
+
+test!(clean_spec_without_lockfile {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies.d1]
+                path = "d1"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("d1/Cargo.toml", r#"
+            [package]
+            name = "d1"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("d1/src/lib.rs", "");
+
+    assert_that(p.cargo_process("build").arg("--release"), execs().with_status(0));
+    fs::remove_file(p.root().join("Cargo.lock")).unwrap();
+
+    assert_that(p.cargo("clean").arg("-p").arg("d1").arg("--release"),
+                execs().with_status(0).with_stdout(""));
+    let deps = p.build_dir().join("release").join("deps");
+    assert!(fs::read_dir(&deps).unwrap().all(|e| {
+        !e.unwrap().file_name().to_str().unwrap().starts_with("libd1")
+    }));
+    assert_that(&p.release_bin("foo"), existing_file());
+    assert_that(&p.root().join("Cargo.lock"), is_not(existing_file()));
+});
+
+test!(clean_spec_dry_run {
+    let p = project("foo")
+              .file("Cargo.toml", &basic_bin_manifest("foo"))
+              .file("src/foo.rs", &main_file(r#""i am foo""#, &[]));
+
+    assert_that(p.cargo_process("build"), execs().with_status(0));
+    assert_that(p.cargo_process("build").arg("--release"), execs().with_status(0));
+
+    let output = p.cargo("clean").arg("-p").arg("foo").arg("--dry-run")
+                  .exec_with_output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let mut lines = stdout.lines().collect::<Vec<_>>();
+    let summary = lines.pop().unwrap();
+
+    // Every path is listed once, and the total counts each of them once.
+    let mut unique = lines.clone();
+    unique.sort();
+    unique.dedup();
+    assert_eq!(unique.len(), lines.len(), "{}", stdout);
+    assert!(lines.contains(&&*p.bin("foo").display().to_string()), "{}", stdout);
+    assert!(lines.contains(&&*p.release_bin("foo").display().to_string()),
+            "{}", stdout);
+    let (files, bytes) = lines.iter().fold((0, 0), |(f, b), path| {
+        let (f2, b2) = paths::disk_usage(Path::new(path)).unwrap();
+        (f + f2, b + b2)
+    });
+    assert_eq!(summary, format!("would remove {} files totalling {} bytes",
+                                files, bytes));
+    assert_that(&p.bin("foo"), existing_file());
+});
@@ -1738,3 +1738,197 @@ test!(denied_lints_are_allowed {
 {compiling} foo v0.0.1 ([..])
 ", compiling = COMPILING, updating = UPDATING)));
 });
+
+test!(only_fetches_requested_branch {
+    let a = git::new("a", |p| {
+        p.file("Cargo.toml", r#"
+            [project]
+            name = "a"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+    }).unwrap();
+
+    // Put an unrelated branch in the repository which should never be fetched
+    let repo = git2::Repository::open(&a.root()).unwrap();
+    let head = repo.head().unwrap().target().unwrap();
+    let head = repo.find_commit(head).unwrap();
+    repo.branch("unrelated", &head, true).unwrap();
+
+    let p = project("foo")
+        .file("Cargo.toml", &format!(r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            a = {{ git = '{}' }}
+        "#, a.url()))
+        .file("src/lib.rs", "");
+
+    assert_that(p.cargo_process("build"),
+                execs().with_status(0));
+
+    let db = paths::home().join(".cargo/git/db");
+    for entry in fs::read_dir(&db).unwrap() {
+        let repo = git2::Repository::open(&entry.unwrap().path()).unwrap();
+        assert!(repo.find_reference("refs/heads/master").is_ok());
+        assert!(repo.find_reference("refs/heads/unrelated").is_err());
+    }
+});
+
+test!(locked_rev_not_on_branch_is_still_fetched {
+    let a = git::new("a", |p| {
+        p.file("Cargo.toml", r#"
+            [project]
+            name = "a"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+    }).unwrap();
+
+    let p = project("foo")
+        .file("Cargo.toml", &format!(r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            a = {{ git = '{}' }}
+        "#, a.url()))
+        .file("src/lib.rs", "");
+
+    assert_that(p.cargo_process("build"),
+                execs().with_status(0));
+    let mut lock1 = String::new();
+    File::open(&p.root().join("Cargo.lock")).unwrap()
+         .read_to_string(&mut lock1).unwrap();
+
+    // Keep the locked commit alive on another branch, then rewrite `master`
+    // so the locked commit is no longer reachable from it.
+    let repo = git2::Repository::open(&a.root()).unwrap();
+    let head = repo.head().unwrap().target().unwrap();
+    let head = repo.find_commit(head).unwrap();
+    repo.branch("old", &head, true).unwrap();
+    let sig = repo.signature().unwrap();
+    let rewritten = repo.commit(None, &sig, &sig, "rewritten",
+                                &head.tree().unwrap(), &[]).unwrap();
+    repo.reference("refs/heads/master", rewritten, true, "force push").unwrap();
+
+    paths::home().join(".cargo/git").rm_rf().unwrap();
+    assert_that(p.cargo("build"),
+                execs().with_status(0));
+
+    let mut lock2 = String::new();
+    File::open(&p.root().join("Cargo.lock")).unwrap()
+         .read_to_string(&mut lock2).unwrap();
+    assert_eq!(lock1, lock2);
+});
+
+test!(fetch_with_git_cli {
+    let a = git::new("a", |p| {
+        p.file("Cargo.toml", r#"
+            [project]
+            name = "a"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+    }).unwrap();
+
+    let p = project("foo")
+        .file("Cargo.toml", &format!(r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            a = {{ git = '{}' }}
+        "#, a.url()))
+        .file("src/lib.rs", "")
+        .file(".cargo/config", r#"
+            [net]
+            git-fetch-with-cli = true
+        "#);
+
+    assert_that(p.cargo_process("build"),
+                execs().with_status(0).with_stdout(&format!("\
+{updating} git repository `[..]`
+{compiling} a v0.5.0 ([..])
+{compiling} foo v0.0.1 ([..])
+", compiling = COMPILING, updating = UPDATING)));
+});
+
+test!(fetch_pinned_rev_with_git_cli_is_shallow {
+    let a = git::new("a", |p| {
+        p.file("Cargo.toml", r#"
+            [project]
+            name = "a"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+    }).unwrap();
+    let repo = git2::Repository::open(&a.root()).unwrap();
+    let old_rev = repo.revparse_single("HEAD").unwrap().id();
+    File::create(&a.root().join("src/lib.rs")).unwrap()
+        .write_all(b"pub fn a() {}").unwrap();
+    git::add(&repo);
+    let rev = git::commit(&repo);
+    repo.config().unwrap().set_bool("uploadpack.allowAnySHA1InWant", true)
+        .unwrap();
+
+    let p = project("foo")
+        .file("Cargo.toml", &format!(r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            a = {{ git = '{}', rev = "{}" }}
+        "#, a.url(), rev))
+        .file("src/lib.rs", "extern crate a; pub fn foo() { a::a() }")
+        .file(".cargo/config", r#"
+            [net]
+            git-fetch-with-cli = true
+        "#);
+
+    assert_that(p.cargo_process("build"), execs().with_status(0));
+
+    // Only the pinned commit was fetched, not the history before it.
+    let db = paths::home().join(".cargo/git/db");
+    for entry in fs::read_dir(&db).unwrap() {
+        let db = git2::Repository::open(&entry.unwrap().path()).unwrap();
+        assert!(db.find_commit(rev).is_ok());
+        assert!(db.find_commit(old_rev).is_err());
+    }
+});
+
+test!(fetch_with_git_cli_reports_errors {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            a = { git = "file:///nonexistent/repo" }
+        "#)
+        .file("src/lib.rs", "")
+        .file(".cargo/config", r#"
+            [net]
+            git-fetch-with-cli = true
+        "#);
+
+    assert_that(p.cargo_process("build").arg("-v"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+  failed to fetch `file:///nonexistent/repo` with the git CLI"));
+});
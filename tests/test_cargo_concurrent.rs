@@ -0,0 +1,54 @@
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::process::Stdio;
+use std::thread;
+
+use support::project;
+
+fn setup() {
+}
+
+test!(second_build_waits_for_build_directory {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            build = "build.rs"
+        "#)
+        .file("build.rs", r#"
+            use std::fs::{self, File};
+            use std::thread;
+
+            fn main() {
+                File::create("started").unwrap();
+                while fs::metadata("go").is_err() {
+                    thread::sleep_ms(100);
+                }
+            }
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    p.build();
+
+    let mut first = p.cargo("build").build_command()
+                     .stdout(Stdio::null()).spawn().unwrap();
+    while fs::metadata(&p.root().join("started")).is_err() {
+        thread::sleep_ms(100);
+    }
+
+    // The first build holds the lock on the build directory until its build
+    // script finishes, so the second one has to say that it's waiting.
+    let mut second = p.cargo("build").build_command()
+                      .stdout(Stdio::piped()).spawn().unwrap();
+    let mut stdout = BufReader::new(second.stdout.take().unwrap());
+    let mut line = String::new();
+    stdout.read_line(&mut line).unwrap();
+    assert!(line.contains("Blocking waiting for file lock on build directory"),
+            "unexpected output: {}", line);
+
+    File::create(&p.root().join("go")).unwrap();
+    assert!(first.wait().unwrap().success());
+    assert!(second.wait().unwrap().success());
+});
@@ -0,0 +1,137 @@
+use std::fs::{self, File};
+use std::io::prelude::*;
+
+use git2;
+
+use support::{execs, paths, cargo_dir};
+use hamcrest::{assert_that, existing_file, existing_dir, is_not};
+
+use cargo::util::{process, ProcessBuilder};
+
+fn setup() {
+}
+
+fn cargo_process(s: &str) -> ProcessBuilder {
+    let mut p = process(&cargo_dir().join("cargo")).unwrap();
+    p.arg(s).cwd(&paths::root()).env("HOME", &paths::home())
+     .env("USER", "foo");
+    return p;
+}
+
+fn read(path: &str) -> String {
+    let mut contents = String::new();
+    File::open(&paths::root().join(path)).unwrap()
+        .read_to_string(&mut contents).unwrap();
+    contents
+}
+
+fn write(path: &str, contents: &str) {
+    let path = paths::root().join(path);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    File::create(&path).unwrap().write_all(contents.as_bytes()).unwrap();
+}
+
+test!(simple_lib {
+    fs::create_dir(&paths::root().join("foo")).unwrap();
+    assert_that(cargo_process("init").arg("foo").arg("--vcs").arg("none"),
+                execs().with_status(0));
+
+    assert_that(&paths::root().join("foo/Cargo.toml"), existing_file());
+    assert_that(&paths::root().join("foo/src/lib.rs"), existing_file());
+    assert_that(&paths::root().join("foo/.gitignore"), is_not(existing_file()));
+
+    assert_that(cargo_process("build").cwd(&paths::root().join("foo")),
+                execs().with_status(0));
+});
+
+test!(current_directory {
+    fs::create_dir(&paths::root().join("foo")).unwrap();
+    assert_that(cargo_process("init").arg("--bin").arg("--vcs").arg("git")
+                                     .cwd(&paths::root().join("foo")),
+                execs().with_status(0));
+
+    assert!(read("foo/Cargo.toml").contains(r#"name = "foo""#));
+    assert_that(&paths::root().join("foo/src/main.rs"), existing_file());
+    assert_that(&paths::root().join("foo/.git"), existing_dir());
+    assert_eq!(read("foo/.gitignore"), "target\n");
+});
+
+test!(existing_main_rs {
+    write("foo/main.rs", "fn main() { println!(\"hello\"); }\n");
+    assert_that(cargo_process("init").arg("foo").arg("--vcs").arg("none"),
+                execs().with_status(0));
+
+    let manifest = read("foo/Cargo.toml");
+    assert!(manifest.contains("[[bin]]\nname = \"foo\"\npath = \"main.rs\"\n"),
+            "{}", manifest);
+    assert_eq!(read("foo/main.rs"), "fn main() { println!(\"hello\"); }\n");
+    assert_that(&paths::root().join("foo/src"), is_not(existing_dir()));
+
+    assert_that(cargo_process("run").cwd(&paths::root().join("foo")),
+                execs().with_status(0).with_stdout_contains("hello"));
+});
+
+test!(existing_name_rs {
+    write("foo/foo.rs", "pub fn foo() {}\n");
+    write("foo/src/main.rs", "extern crate foo; fn main() { foo::foo() }\n");
+    assert_that(cargo_process("init").arg("foo").arg("--vcs").arg("none"),
+                execs().with_status(0));
+
+    let manifest = read("foo/Cargo.toml");
+    assert!(manifest.contains("[lib]\npath = \"foo.rs\"\n"), "{}", manifest);
+    assert!(!manifest.contains("[[bin]]"), "{}", manifest);
+    assert_that(&paths::root().join("foo/src/lib.rs"), is_not(existing_file()));
+
+    assert_that(cargo_process("build").cwd(&paths::root().join("foo")),
+                execs().with_status(0));
+});
+
+test!(existing_src_lib_rs_untouched {
+    write("foo/src/lib.rs", "pub fn foo() {}\n");
+    assert_that(cargo_process("init").arg("foo").arg("--vcs").arg("none"),
+                execs().with_status(0));
+
+    assert_eq!(read("foo/src/lib.rs"), "pub fn foo() {}\n");
+    assert!(!read("foo/Cargo.toml").contains("[lib]"));
+});
+
+test!(existing_gitignore {
+    write("foo/.gitignore", "*.o\ntarget");
+    assert_that(cargo_process("init").arg("foo").arg("--vcs").arg("git"),
+                execs().with_status(0));
+
+    assert_eq!(read("foo/.gitignore"), "*.o\ntarget\nCargo.lock\n");
+    assert_that(&paths::root().join("foo/.git"), existing_dir());
+});
+
+test!(existing_cargo_toml {
+    write("foo/Cargo.toml", "");
+    assert_that(cargo_process("init").arg("foo"),
+                execs().with_status(101).with_stderr("\
+`cargo init` cannot be run on existing Cargo projects
+"));
+});
+
+test!(missing_directory {
+    assert_that(cargo_process("init").arg("foo"),
+                execs().with_status(101).with_stderr("\
+cannot initialize `[..]foo` as a project, it is not an existing directory
+"));
+});
+
+test!(existing_repository {
+    fs::create_dir(&paths::root().join("foo")).unwrap();
+    git2::Repository::init(&paths::root().join("foo")).unwrap();
+    write("foo/.gitignore", "*.o\n");
+    write("foo/bar/src/lib.rs", "pub fn bar() {}\n");
+
+    assert_that(cargo_process("init").arg("foo"),
+                execs().with_status(0));
+    assert_eq!(read("foo/.gitignore"), "*.o\ntarget\nCargo.lock\n");
+
+    // A project in a subdirectory of the repository is added to it as well
+    assert_that(cargo_process("init").arg("foo/bar"),
+                execs().with_status(0));
+    assert_eq!(read("foo/bar/.gitignore"), "target\nCargo.lock\n");
+    assert_that(&paths::root().join("foo/bar/.git"), is_not(existing_dir()));
+});
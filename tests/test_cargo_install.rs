@@ -7,7 +7,7 @@ use cargo::util::{process, ProcessBuilder};
 use hamcrest::{assert_that, existing_file, is_not, Matcher, MatchResult};
 
 use support::{project, execs, cargo_dir};
-use support::{UPDATING, DOWNLOADING, COMPILING, INSTALLING, REMOVING};
+use support::{UPDATING, DOWNLOADING, COMPILING, INSTALLING, REMOVING, REPLACING};
 use support::paths;
 use support::registry as r;
 use support::git;
@@ -370,6 +370,7 @@ test!(install_twice {
     assert_that(cargo_process("install").arg("--path").arg(p.root()),
                 execs().with_status(101).with_stderr("\
 binary `foo[..]` already exists in destination as part of `foo v0.1.0 ([..])`
+Add --force to overwrite
 "));
 });
 
@@ -498,3 +499,398 @@ test!(uninstall_piecemeal {
 package id specification `foo` matched no packages
 "));
 });
+
+test!(install_force {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    p.build();
+
+    assert_that(cargo_process("install").arg("--path").arg(p.root()),
+                execs().with_status(0));
+    assert_that(cargo_process("install").arg("--force").arg("--path").arg(p.root()),
+                execs().with_status(0).with_stdout(&format!("\
+{compiling} foo v0.1.0 ([..])
+{replacing} {home}[..]bin[..]foo[..]
+",
+        compiling = COMPILING,
+        replacing = REPLACING,
+        home = cargo_home().display())));
+    assert_that(cargo_home(), has_installed_exe("foo"));
+});
+
+test!(upgrade {
+    r::mock_pkg("foo", "0.0.1", &[]);
+
+    assert_that(cargo_process("install").arg("foo"),
+                execs().with_status(0));
+
+    r::mock_pkg("foo", "0.0.2", &[]);
+
+    assert_that(cargo_process("install").arg("--list").arg("--outdated"),
+                execs().with_status(0).with_stdout(&format!("\
+{updating} registry `[..]`
+foo v0.0.1 (registry [..]) -> v0.0.2
+", updating = UPDATING)));
+    assert_that(cargo_process("install").arg("foo"),
+                execs().with_status(0).with_stdout(&format!("\
+{updating} registry `[..]`
+{downloading} foo v0.0.2 (registry file://[..])
+{compiling} foo v0.0.2 (registry file://[..])
+{replacing} {home}[..]bin[..]foo[..]
+",
+        updating = UPDATING,
+        downloading = DOWNLOADING,
+        compiling = COMPILING,
+        replacing = REPLACING,
+        home = cargo_home().display())));
+    assert_that(cargo_process("install").arg("--list"),
+                execs().with_status(0).with_stdout("\
+foo v0.0.2 (registry [..]):
+    foo[..]
+"));
+    assert_that(cargo_process("install").arg("--list").arg("--outdated"),
+                execs().with_status(0).with_stdout_contains("\
+All installed packages are up to date
+"));
+
+    // The listing stays readable by older versions of Cargo, which only know
+    // about `v1`, while the build details are kept next to it.
+    let mut listing = String::new();
+    File::open(cargo_home().join(".crates.toml")).unwrap()
+        .read_to_string(&mut listing).unwrap();
+    assert!(listing.starts_with("[v1]"), "{}", listing);
+    assert!(!listing.contains("profile"), "{}", listing);
+    let mut details = String::new();
+    File::open(cargo_home().join(".crates2.toml")).unwrap()
+        .read_to_string(&mut details).unwrap();
+    assert!(details.contains("profile = \"release\""), "{}", details);
+    assert!(details.contains("rustc = \"rustc "), "{}", details);
+});
+
+test!(listing_changed_by_older_cargo {
+    r::mock_pkg("foo", "0.0.1", &[]);
+    r::mock_pkg("bar", "0.0.1", &[]);
+    assert_that(cargo_process("install").arg("foo").arg("bar"),
+                execs().with_status(0));
+
+    // An older Cargo uninstalling `foo` only updates `.crates.toml`.
+    let mut listing = String::new();
+    File::open(cargo_home().join(".crates.toml")).unwrap()
+        .read_to_string(&mut listing).unwrap();
+    let listing = listing.lines().filter(|l| !l.starts_with("\"foo "))
+                         .fold(String::new(), |s, l| s + l + "\n");
+    File::create(cargo_home().join(".crates.toml")).unwrap()
+        .write_all(listing.as_bytes()).unwrap();
+
+    assert_that(cargo_process("install").arg("--list"),
+                execs().with_status(0).with_stdout("\
+bar v0.0.1 (registry [..]):
+    bar[..]
+"));
+});
+
+test!(locked_requires_lockfile {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    p.build();
+
+    assert_that(cargo_process("install").arg("--locked").arg("--path").arg(p.root()),
+                execs().with_status(101).with_stderr_contains("\
+  the lock file [..]Cargo.lock needs to be present when --locked is passed
+"));
+    assert_that(cargo_home(), is_not(has_installed_exe("foo")));
+});
+
+test!(locked_uses_lockfile {
+    r::mock_pkg("bar", "0.1.0", &[]);
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    p.build();
+    assert_that(p.cargo("generate-lockfile"), execs().with_status(0));
+
+    // A newer version is ignored in favor of the locked one.
+    r::mock_pkg("bar", "0.1.1", &[]);
+    assert_that(cargo_process("install").arg("--locked").arg("--path").arg(p.root()),
+                execs().with_status(0).with_stdout_contains(&format!("\
+{compiling} bar v0.1.0 (registry [..])
+", compiling = COMPILING)));
+    assert_that(cargo_home(), has_installed_exe("foo"));
+
+    // Requiring a version the lockfile doesn't satisfy fails.
+    File::create(&p.root().join("Cargo.toml")).unwrap().write_all(br#"
+        [package]
+        name = "foo"
+        version = "0.1.0"
+        authors = []
+
+        [dependencies]
+        bar = "0.1.1"
+    "#).unwrap();
+    assert_that(cargo_process("install").arg("--locked").arg("--force")
+                                        .arg("--path").arg(p.root()),
+                execs().with_status(101).with_stderr_contains("\
+  the lock file [..]Cargo.lock needs to be updated but --locked was passed to \
+prevent this
+"));
+});
+
+// The lock file a version of `foo` depending on `bar = "0.1"` would ship.
+fn foo_lockfile(version: &str) -> String {
+    let p = project(&format!("lock-{}", version))
+        .file("Cargo.toml", &format!(r#"
+            [package]
+            name = "foo"
+            version = "{}"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+        "#, version))
+        .file("src/main.rs", "fn main() {}");
+    p.build();
+    assert_that(p.cargo("generate-lockfile"), execs().with_status(0));
+    let mut lock = String::new();
+    File::open(p.root().join("Cargo.lock")).unwrap()
+        .read_to_string(&mut lock).unwrap();
+    lock
+}
+
+test!(locked_uses_shipped_lockfile {
+    r::mock_pkg("bar", "0.1.0", &[]);
+    let lock1 = foo_lockfile("0.1.0");
+    let lock2 = foo_lockfile("0.2.0");
+    r::mock_pkg("bar", "0.1.1", &[]);
+
+    // The lock file shipped with the crate keeps `bar` at 0.1.0.
+    r::mock_pkg_files("foo", "0.1.0", &[("bar", "0.1", "normal")],
+                      &[("Cargo.lock", &lock1)], false);
+    assert_that(cargo_process("install").arg("--locked").arg("foo"),
+                execs().with_status(0).with_stdout_contains(&format!("\
+{compiling} bar v0.1.0 (registry [..])
+", compiling = COMPILING)));
+    assert_that(cargo_home(), has_installed_exe("foo"));
+
+    // A crate whose shipped lock file doesn't satisfy its manifest anymore
+    // isn't installed with whatever resolves instead.
+    r::mock_pkg_files("foo", "0.2.0", &[("bar", "0.1.1", "normal")],
+                      &[("Cargo.lock", &lock2)], false);
+    assert_that(cargo_process("install").arg("--locked").arg("foo")
+                                        .arg("--vers").arg("0.2.0"),
+                execs().with_status(101).with_stderr_contains("\
+  the lock file [..]Cargo.lock needs to be updated but --locked was passed to \
+prevent this
+"));
+    assert_that(cargo_process("install").arg("--list"),
+                execs().with_status(0).with_stdout("\
+foo v0.1.0 (registry [..]):
+    foo[..]
+"));
+});
+
+test!(shipped_lockfile_ignored_without_locked {
+    r::mock_pkg("bar", "0.1.0", &[]);
+    let lock = foo_lockfile("0.1.0");
+    r::mock_pkg("bar", "0.1.1", &[]);
+    r::mock_pkg_files("foo", "0.1.0", &[("bar", "0.1", "normal")],
+                      &[("Cargo.lock", &lock)], false);
+
+    // Without --locked the newest compatible `bar` is used, as if the crate
+    // hadn't shipped a lock file.
+    assert_that(cargo_process("install").arg("foo"),
+                execs().with_status(0).with_stdout_contains(&format!("\
+{compiling} bar v0.1.1 (registry [..])
+", compiling = COMPILING)));
+    assert_that(cargo_home(), has_installed_exe("foo"));
+});
+
+test!(target_dir_is_reused {
+    r::mock_pkg("bar", "0.0.1", &[]);
+    r::mock_pkg("foo", "0.0.1", &[("bar", "*", "normal")]);
+    r::mock_pkg("baz", "0.0.1", &[("bar", "*", "normal")]);
+
+    assert_that(cargo_process("install").arg("foo"),
+                execs().with_status(0));
+    assert!(cargo_home().join("target-install").is_dir());
+    assert!(!paths::root().join("target-install").exists());
+
+    assert_that(cargo_process("install").arg("baz"),
+                execs().with_status(0).with_stdout(&format!("\
+{updating} registry `[..]`
+{downloading} baz v0.0.1 (registry file://[..])
+{compiling} baz v0.0.1 (registry file://[..])
+{installing} {home}[..]bin[..]baz[..]
+",
+        updating = UPDATING,
+        downloading = DOWNLOADING,
+        compiling = COMPILING,
+        installing = INSTALLING,
+        home = cargo_home().display())));
+});
+
+test!(target_dir_config {
+    r::mock_pkg("foo", "0.0.1", &[]);
+
+    fs::create_dir(paths::root().join(".cargo")).unwrap();
+    File::create(paths::root().join(".cargo/config")).unwrap().write_all(b"
+        [install]
+        target-dir = 'install-cache'
+    ").unwrap();
+
+    assert_that(cargo_process("install").arg("foo"),
+                execs().with_status(0));
+    assert!(paths::root().join("install-cache/release").is_dir());
+    assert!(!cargo_home().join("target-install").exists());
+});
+
+test!(target_dir_config_relative_to_config {
+    r::mock_pkg("foo", "0.0.1", &[]);
+
+    fs::create_dir(paths::root().join(".cargo")).unwrap();
+    File::create(paths::root().join(".cargo/config")).unwrap().write_all(b"
+        [install]
+        target-dir = 'install-cache'
+    ").unwrap();
+    fs::create_dir(paths::root().join("sub")).unwrap();
+
+    assert_that(cargo_process("install").arg("foo").cwd(paths::root().join("sub")),
+                execs().with_status(0));
+    assert!(paths::root().join("install-cache/release").is_dir());
+    assert!(!paths::root().join("sub/install-cache").exists());
+});
+
+test!(multiple_crates {
+    r::mock_pkg("foo", "0.0.1", &[]);
+    r::mock_pkg("bar", "0.0.2", &[]);
+
+    assert_that(cargo_process("install").arg("foo").arg("bar"),
+                execs().with_status(0));
+    assert_that(cargo_home(), has_installed_exe("foo"));
+    assert_that(cargo_home(), has_installed_exe("bar"));
+
+    assert_that(cargo_process("install").arg("--list"),
+                execs().with_status(0).with_stdout("\
+bar v0.0.2 (registry [..]):
+    bar[..]
+foo v0.0.1 (registry [..]):
+    foo[..]
+"));
+});
+
+test!(multiple_crates_report_failures {
+    r::mock_pkg("foo", "0.0.1", &[]);
+
+    assert_that(cargo_process("install").arg("baz").arg("foo").arg("qux"),
+                execs().with_status(101).with_stderr("\
+failed to install `baz`: could not find `baz` in `registry file://[..]`
+failed to install `qux`: could not find `qux` in `registry file://[..]`
+failed to install 2 of the 3 requested crates, so none were installed: `baz`, `qux`
+"));
+    assert_that(cargo_home(), is_not(has_installed_exe("foo")));
+
+    assert_that(cargo_process("install").arg("--list"),
+                execs().with_status(0).with_stdout(""));
+});
+
+test!(multiple_crates_second_fails_to_build {
+    r::mock_pkg("foo", "0.0.1", &[]);
+    assert_that(cargo_process("install").arg("foo"),
+                execs().with_status(0));
+
+    let p = git::repo(&paths::root().join("repo"))
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("a/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("a/src/main.rs", "fn main() { bad }");
+    p.build();
+
+    // `foo` builds but isn't installed, as `bar` fails, so the previous
+    // installation of `foo` is left alone.
+    assert_that(cargo_process("install").arg("--git").arg(p.url().to_string())
+                                        .arg("foo").arg("bar").arg("--force"),
+                execs().with_status(101)
+                       .with_stdout_contains(&format!("{} foo v0.1.0 ([..])",
+                                                      COMPILING))
+                       .with_stderr_contains("\
+failed to install `bar`: failed to compile `bar v0.1.0 ([..])`[..]")
+                       .with_stderr_contains("\
+failed to install 1 of the 2 requested crates, so none were installed: `bar`"));
+    assert_that(cargo_home(), has_installed_exe("foo"));
+    assert_that(cargo_home(), is_not(has_installed_exe("bar")));
+    let staged = fs::read_dir(cargo_home().join("bin")).unwrap().map(|e| {
+        e.unwrap().file_name().into_string().unwrap()
+    }).filter(|name| name.ends_with(".cargo-install")).collect::<Vec<_>>();
+    assert!(staged.is_empty(), "{:?}", staged);
+
+    assert_that(cargo_process("install").arg("--list"),
+                execs().with_status(0).with_stdout("\
+foo v0.0.1 (registry [..]):
+    foo[..]
+"));
+});
+
+test!(multiple_crates_git_repo {
+    let p = git::repo(&paths::root().join("foo"))
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("a/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("a/src/main.rs", "fn main() {}");
+    p.build();
+
+    assert_that(cargo_process("install").arg("--git").arg(p.url().to_string())
+                                        .arg("foo").arg("bar"),
+                execs().with_status(0).with_stdout(&format!("\
+{updating} git repository `[..]`
+{compiling} foo v0.1.0 ([..])
+{compiling} bar v0.1.0 ([..])
+{installing} {home}[..]bin[..]foo[..]
+{installing} {home}[..]bin[..]bar[..]
+",
+        updating = UPDATING,
+        compiling = COMPILING,
+        installing = INSTALLING,
+        home = cargo_home().display())));
+    assert_that(cargo_home(), has_installed_exe("foo"));
+    assert_that(cargo_home(), has_installed_exe("bar"));
+});
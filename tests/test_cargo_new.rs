@@ -1,9 +1,12 @@
 use std::fs::{self, File};
 use std::io::prelude::*;
 use std::env;
+use std::path::Path;
+use git2;
 use tempdir::TempDir;
 
 use support::{execs, paths, cargo_dir};
+use support::git;
 use support::paths::CargoPathExt;
 use hamcrest::{assert_that, existing_file, existing_dir, is_not};
 
@@ -285,3 +288,144 @@ Usage:
     cargo new [..]
 "));
 });
+
+fn template(dir: &str) {
+    let dir = paths::root().join(dir);
+    fs::create_dir_all(&dir.join("src")).unwrap();
+    File::create(&dir.join("Cargo.toml")).unwrap().write_all(b"\
+[package]
+name = \"{{name}}\"
+version = \"0.1.0\"
+authors = [\"{{authors}}\"]
+").unwrap();
+    File::create(&dir.join("src/lib.rs")).unwrap().write_all(b"\
+// Copyright {{year}} {{authors}}
+#![deny(missing_docs)]
+//! The {{name}} crate
+").unwrap();
+    File::create(&dir.join(".travis.yml")).unwrap()
+        .write_all(b"language: rust\n").unwrap();
+}
+
+fn read(path: &Path) -> String {
+    let mut contents = String::new();
+    File::open(path).unwrap().read_to_string(&mut contents).unwrap();
+    contents
+}
+
+test!(template_dir {
+    template("tmpl");
+    assert_that(cargo_process("new").arg("foo").arg("--vcs").arg("none")
+                                    .arg("--template").arg("tmpl")
+                                    .env("USER", "foo"),
+                execs().with_status(0));
+
+    let root = paths::root().join("foo");
+    assert_eq!(read(&root.join("Cargo.toml")), "\
+[package]
+name = \"foo\"
+version = \"0.1.0\"
+authors = [\"foo\"]
+");
+    let lib = read(&root.join("src/lib.rs"));
+    assert!(lib.starts_with("// Copyright 2"), "{}", lib);
+    assert!(lib.contains(" foo\n#![deny(missing_docs)]\n//! The foo crate\n"), "{}", lib);
+    assert_that(&root.join(".travis.yml"), existing_file());
+    assert_that(&root.join("src/main.rs"), is_not(existing_file()));
+
+    assert_that(cargo_process("build").cwd(&root),
+                execs().with_status(0));
+});
+
+test!(template_escapes_manifest {
+    template("tmpl");
+    assert_that(cargo_process("new").arg("foo").arg("--vcs").arg("none")
+                                    .arg("--template").arg("tmpl")
+                                    .env("USER", "foo \"bar\""),
+                execs().with_status(0));
+
+    let root = paths::root().join("foo");
+    assert_eq!(read(&root.join("Cargo.toml")), "\
+[package]
+name = \"foo\"
+version = \"0.1.0\"
+authors = [\"foo \\\"bar\\\"\"]
+");
+    let lib = read(&root.join("src/lib.rs"));
+    assert!(lib.contains(" foo \"bar\"\n"), "{}", lib);
+
+    assert_that(cargo_process("build").cwd(&root),
+                execs().with_status(0));
+});
+
+test!(template_from_config {
+    template("tmpl");
+    fs::create_dir(&paths::root().join(".cargo")).unwrap();
+    File::create(&paths::root().join(".cargo/config")).unwrap().write_all(br#"
+        [cargo-new]
+        template = "tmpl"
+    "#).unwrap();
+
+    assert_that(cargo_process("new").arg("foo").arg("--vcs").arg("git")
+                                    .env("USER", "foo"),
+                execs().with_status(0));
+
+    let root = paths::root().join("foo");
+    assert_that(&root.join(".travis.yml"), existing_file());
+    assert_eq!(read(&root.join(".gitignore")), "target\nCargo.lock\n");
+});
+
+test!(template_git_repo {
+    let repo = git::repo(&paths::root().join("tmpl"))
+        .file("src/main.rs", "// {{name}}\nfn main() {}\n")
+        .file("README.md", "# {{name}}\n");
+    repo.build();
+
+    assert_that(cargo_process("new").arg("foo").arg("--vcs").arg("none")
+                                    .arg("--template").arg(repo.url().to_string())
+                                    .env("USER", "foo"),
+                execs().with_status(0));
+
+    let root = paths::root().join("foo");
+    assert_eq!(read(&root.join("README.md")), "# foo\n");
+    assert_eq!(read(&root.join("src/main.rs")), "// foo\nfn main() {}\n");
+    assert!(read(&root.join("Cargo.toml")).contains("name = \"foo\""));
+    assert_that(&root.join("src/lib.rs"), is_not(existing_file()));
+    assert_that(&root.join(".git"), is_not(existing_dir()));
+});
+
+test!(template_git_branch {
+    let repo = git::repo(&paths::root().join("tmpl"))
+        .file("README.md", "# {{name}}\n");
+    repo.build();
+    let git_repo = git2::Repository::open(&paths::root().join("tmpl")).unwrap();
+    let head = git_repo.head().unwrap().target().unwrap();
+    let head = git_repo.find_commit(head).unwrap();
+    git_repo.branch("other", &head, true).unwrap();
+    File::create(&paths::root().join("tmpl/NEWS.md")).unwrap()
+        .write_all(b"only on master\n").unwrap();
+    git::add(&git_repo);
+    git::commit(&git_repo);
+
+    assert_that(cargo_process("new").arg("foo").arg("--vcs").arg("none")
+                                    .arg("--template")
+                                    .arg(format!("{}#other", repo.url()))
+                                    .env("USER", "foo"),
+                execs().with_status(0));
+
+    let root = paths::root().join("foo");
+    assert_eq!(read(&root.join("README.md")), "# foo\n");
+    assert_that(&root.join("NEWS.md"), is_not(existing_file()));
+});
+
+test!(template_missing {
+    assert_that(cargo_process("new").arg("foo").arg("--template").arg("nope")
+                                    .env("USER", "foo"),
+                execs().with_status(101).with_stderr("\
+Failed to create project `foo` at `[..]foo`
+
+Caused by:
+  the template `[..]nope` is not a directory
+"));
+    assert_that(&paths::root().join("foo"), is_not(existing_dir()));
+});
@@ -0,0 +1,107 @@
+use support::{project, execs, UPDATING};
+use support::registry as r;
+
+use hamcrest::assert_that;
+
+fn setup() {
+    r::init();
+}
+
+test!(up_to_date {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    p.build();
+
+    r::mock_pkg("bar", "0.1.0", &[]);
+
+    assert_that(p.cargo("generate-lockfile"), execs().with_status(0));
+    assert_that(p.cargo("outdated"),
+                execs().with_status(0).with_stdout(&format!("\
+{updating} registry `{reg}`
+All dependencies are up to date
+",
+        updating = UPDATING,
+        reg = r::registry())));
+});
+
+test!(newer_versions {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    p.build();
+
+    r::mock_pkg("baz", "1.0.0", &[]);
+    r::mock_pkg("bar", "0.1.0", &[("baz", "1.0", "normal")]);
+
+    assert_that(p.cargo("generate-lockfile"), execs().with_status(0));
+
+    r::mock_pkg("bar", "0.1.1", &[("baz", "1.0", "normal")]);
+    r::mock_pkg("bar", "0.2.0", &[("baz", "1.0", "normal")]);
+    r::mock_pkg_yank("bar", "0.3.0", &[], true);
+    r::mock_pkg("baz", "2.0.0", &[]);
+
+    assert_that(p.cargo("outdated"),
+                execs().with_status(0).with_stdout(&format!("\
+{updating} registry `{reg}`
+Name               Current   Compatible   Latest
+bar                0.1.0     0.1.1        0.2.0
+baz (transitive)   1.0.0     -            2.0.0
+",
+        updating = UPDATING,
+        reg = r::registry())));
+
+    assert_that(p.cargo("outdated").arg("--json"),
+                execs().with_status(0).with_stdout_contains("\
+[{\"name\":\"bar\",\"source\":\"registry+file://[..]\",\"current\":\"0.1.0\",\
+\"compatible\":\"0.1.1\",\"latest\":\"0.2.0\",\"direct\":true},\
+{\"name\":\"baz\",\"source\":\"registry+file://[..]\",\"current\":\"1.0.0\",\
+\"compatible\":null,\"latest\":\"2.0.0\",\"direct\":false}]"));
+});
+
+test!(build_metadata {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    p.build();
+
+    r::mock_pkg("bar", "0.1.0+build.1", &[]);
+
+    assert_that(p.cargo("generate-lockfile"), execs().with_status(0));
+
+    r::mock_pkg("bar", "0.1.1", &[]);
+    r::mock_pkg("bar", "0.2.0-beta", &[]);
+
+    assert_that(p.cargo("outdated"),
+                execs().with_status(0).with_stdout(&format!("\
+{updating} registry `{reg}`
+Name   Current         Compatible   Latest
+bar    0.1.0+build.1   0.1.1        0.2.0-beta
+",
+        updating = UPDATING,
+        reg = r::registry())));
+});
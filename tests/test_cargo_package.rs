@@ -1,9 +1,9 @@
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::Cursor;
 use std::io::prelude::*;
 use std::path::Path;
 
-use cargo::util::process;
+use cargo::util::{process, to_hex, Sha256};
 use flate2::read::GzDecoder;
 use git2;
 use tar::Archive;
@@ -46,6 +46,7 @@ test!(simple {
     assert_that(p.cargo("package").arg("-l"),
                 execs().with_status(0).with_stdout("\
 Cargo.toml
+Cargo.toml.orig
 src[..]main.rs
 "));
     assert_that(p.cargo("package"),
@@ -61,6 +62,7 @@ src[..]main.rs
         let fname = f.header().path_bytes();
         let fname = &*fname;
         assert!(fname == b"foo-0.0.1/Cargo.toml" ||
+                fname == b"foo-0.0.1/Cargo.toml.orig" ||
                 fname == b"foo-0.0.1/src/main.rs",
                 "unexpected filename: {:?}", f.header().path())
     }
@@ -223,6 +225,7 @@ test!(package_verbose {
     assert_that(cargo.arg("package").arg("-v").arg("--no-verify"),
                 execs().with_status(0).with_stdout(&format!("\
 {packaging} foo v0.0.1 ([..])
+{archiving} Cargo.lock
 {archiving} [..]
 {archiving} [..]
 ",
@@ -333,7 +336,7 @@ test!(package_new_git_repo {
     git2::Repository::init(&p.root()).unwrap();
 
     assert_that(p.process(cargo_dir().join("cargo")).arg("package")
-                 .arg("--no-verify").arg("-v"),
+                 .arg("--no-verify").arg("--allow-dirty").arg("-v"),
                 execs().with_status(0).with_stdout(&format!("\
 {packaging} foo v0.0.1 ([..])
 {archiving} [..]
@@ -408,6 +411,7 @@ test!(ignore_nested {
     assert_that(p.cargo("package").arg("-l"),
                 execs().with_status(0).with_stdout("\
 Cargo.toml
+Cargo.toml.orig
 src[..]main.rs
 "));
     assert_that(p.cargo("package"),
@@ -423,7 +427,263 @@ src[..]main.rs
         let fname = f.header().path_bytes();
         let fname = &*fname;
         assert!(fname == b"nested-0.0.1/Cargo.toml" ||
+                fname == b"nested-0.0.1/Cargo.toml.orig" ||
                 fname == b"nested-0.0.1/src/main.rs",
                 "unexpected filename: {:?}", f.header().path())
     }
 });
+
+test!(normalized_manifest {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "foo"
+
+            [dependencies.bar]
+            path = "bar"
+            version = "0.1.0"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("src/bin/other.rs", "fn main() {}")
+        .file("bar/Cargo.toml", r#"
+            [project]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "");
+
+    assert_that(p.cargo_process("package").arg("--no-verify"),
+                execs().with_status(0));
+
+    let f = File::open(&p.root().join("target/package/foo-0.0.1.crate")).unwrap();
+    let mut rdr = GzDecoder::new(f).unwrap();
+    let mut contents = Vec::new();
+    rdr.read_to_end(&mut contents).unwrap();
+    let ar = Archive::new(Cursor::new(contents));
+    let mut normalized = None;
+    let mut original = None;
+    for f in ar.files().unwrap() {
+        let mut f = f.unwrap();
+        let fname = f.header().path_bytes().into_owned();
+        let mut contents = String::new();
+        f.read_to_string(&mut contents).unwrap();
+        if fname == b"foo-0.0.1/Cargo.toml" {
+            normalized = Some(contents);
+        } else if fname == b"foo-0.0.1/Cargo.toml.orig" {
+            original = Some(contents);
+        }
+    }
+
+    let normalized = normalized.unwrap();
+    assert!(!normalized.contains("path = \"bar\""), "{}", normalized);
+    assert!(normalized.contains("version = \"0.1.0\""), "{}", normalized);
+    assert!(normalized.contains("path = \"src/main.rs\""), "{}", normalized);
+    assert!(normalized.contains("path = \"src/bin/other.rs\""), "{}", normalized);
+    assert!(original.unwrap().contains("path = \"bar\""));
+});
+
+test!(normalized_manifest_skips_excluded_targets {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "foo"
+            exclude = ["tests/*"]
+        "#)
+        .file("src/lib.rs", "")
+        .file("tests/excluded.rs", "");
+
+    assert_that(p.cargo_process("package").arg("--no-verify"),
+                execs().with_status(0));
+
+    let f = File::open(&p.root().join("target/package/foo-0.0.1.crate")).unwrap();
+    let mut rdr = GzDecoder::new(f).unwrap();
+    let mut contents = Vec::new();
+    rdr.read_to_end(&mut contents).unwrap();
+    let ar = Archive::new(Cursor::new(contents));
+    let mut normalized = None;
+    for f in ar.files().unwrap() {
+        let mut f = f.unwrap();
+        let fname = f.header().path_bytes().into_owned();
+        assert!(fname != b"foo-0.0.1/tests/excluded.rs");
+        if fname == b"foo-0.0.1/Cargo.toml" {
+            let mut contents = String::new();
+            f.read_to_string(&mut contents).unwrap();
+            normalized = Some(contents);
+        }
+    }
+
+    let normalized = normalized.unwrap();
+    assert!(normalized.contains("path = \"src/lib.rs\""), "{}", normalized);
+    assert!(!normalized.contains("[[test]]"), "{}", normalized);
+    assert!(!normalized.contains("tests/excluded.rs"), "{}", normalized);
+});
+
+test!(reproducible_tarballs {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "foo"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("src/a.rs", "")
+        .file("src/b.rs", "");
+
+    let crate_file = p.root().join("target/package/foo-0.0.1.crate");
+    assert_that(p.cargo_process("package").arg("--no-verify"),
+                execs().with_status(0));
+    let mut first = Vec::new();
+    File::open(&crate_file).unwrap().read_to_end(&mut first).unwrap();
+
+    // Touch a source file and package again from scratch.
+    fs::remove_file(&crate_file).unwrap();
+    File::create(&p.root().join("src/a.rs")).unwrap();
+    assert_that(p.cargo("package").arg("--no-verify"),
+                execs().with_status(0));
+    let mut second = Vec::new();
+    File::open(&crate_file).unwrap().read_to_end(&mut second).unwrap();
+    assert!(first == second, "tarballs differ between runs");
+
+    let mut contents = Vec::new();
+    GzDecoder::new(&first[..]).unwrap().read_to_end(&mut contents).unwrap();
+    let ar = Archive::new(Cursor::new(contents));
+    let names = ar.files().unwrap().map(|f| {
+        let f = f.unwrap();
+        assert_eq!(f.header().mtime().unwrap(), 1153704088);
+        f.header().path_bytes().into_owned()
+    }).collect::<Vec<_>>();
+    let mut sorted = names.clone();
+    sorted.sort();
+    assert_eq!(names, sorted);
+
+    assert_that(p.cargo("package").arg("--no-verify").arg("--print-checksum"),
+                execs().with_status(0).with_stdout(&format!("{}\n",
+                    hex_sha256(&first))));
+});
+
+test!(stale_tarball_is_rebuilt {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "foo"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("src/extra.rs", "");
+
+    let crate_file = p.root().join("target/package/foo-0.0.1.crate");
+    let read_crate = || {
+        let mut contents = Vec::new();
+        File::open(&crate_file).unwrap().read_to_end(&mut contents).unwrap();
+        contents
+    };
+    assert_that(p.cargo_process("package").arg("--no-verify"),
+                execs().with_status(0));
+    let first = read_crate();
+
+    // A file which is no longer packaged.
+    fs::remove_file(&p.root().join("src/extra.rs")).unwrap();
+    assert_that(p.cargo("package").arg("--no-verify"),
+                execs().with_status(0).with_stdout(&format!("\
+{packaging} foo v0.0.1 ([..])
+", packaging = PACKAGING)));
+    let second = read_crate();
+    assert!(first != second);
+
+    // A file modified after the tarball was made.
+    File::create(&p.root().join("src/main.rs")).unwrap()
+        .write_all(b"fn main() { println!(\"changed\") }").unwrap();
+    assert_that(p.cargo("package").arg("--no-verify").arg("--print-checksum"),
+                execs().with_status(0));
+    let third = read_crate();
+    assert!(second != third);
+    assert_that(p.cargo("package").arg("--no-verify").arg("--print-checksum"),
+                execs().with_status(0).with_stdout(&format!("{}\n",
+                    hex_sha256(&third))));
+});
+
+fn hex_sha256(data: &[u8]) -> String {
+    let mut state = Sha256::new();
+    state.update(data);
+    to_hex(&state.finish())
+}
+
+test!(dirty_git_repo {
+    let p = git::new("foo", |p| {
+        p.file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "foo"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+    }).unwrap();
+    File::create(&p.root().join("src/main.rs")).unwrap()
+        .write_all(b"fn main() { println!(\"debug\"); }").unwrap();
+    File::create(&p.root().join("src/extra.rs")).unwrap();
+
+    assert_that(p.cargo("package").arg("--no-verify"),
+                execs().with_status(101).with_stderr("\
+2 files in the working directory contain changes that were not yet committed \
+into git:
+
+src/extra.rs
+src/main.rs
+
+to proceed despite this, pass the `--allow-dirty` flag
+"));
+    assert_that(p.cargo("package").arg("--no-verify").arg("--allow-dirty"),
+                execs().with_status(0));
+});
+
+test!(vcs_info_is_recorded {
+    let p = git::new("foo", |p| {
+        p.file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "foo"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+    }).unwrap();
+    let repo = git2::Repository::open(&p.root()).unwrap();
+    let head = repo.head().unwrap().target().unwrap();
+
+    assert_that(p.cargo("package").arg("--no-verify"),
+                execs().with_status(0));
+
+    let f = File::open(&p.root().join("target/package/foo-0.0.1.crate")).unwrap();
+    let mut rdr = GzDecoder::new(f).unwrap();
+    let mut contents = Vec::new();
+    rdr.read_to_end(&mut contents).unwrap();
+    let ar = Archive::new(Cursor::new(contents));
+    let mut vcs_info = None;
+    for f in ar.files().unwrap() {
+        let mut f = f.unwrap();
+        if &*f.header().path_bytes() == b"foo-0.0.1/.cargo_vcs_info.json" {
+            let mut s = String::new();
+            f.read_to_string(&mut s).unwrap();
+            vcs_info = Some(s);
+        }
+    }
+    assert_eq!(vcs_info.unwrap(), format!("{{\"git\":{{\"sha1\":\"{}\"}}}}", head));
+});
@@ -8,7 +8,7 @@ use tar::Archive;
 use url::Url;
 
 use support::{project, execs};
-use support::{UPDATING, PACKAGING, UPLOADING};
+use support::{UPDATING, PACKAGING, UPLOADING, VERIFYING, COMPILING};
 use support::paths;
 use support::git::repo;
 
@@ -83,6 +83,7 @@ test!(simple {
         let fname = file.header().path_bytes();
         let fname = &*fname;
         assert!(fname == b"foo-0.0.1/Cargo.toml" ||
+                fname == b"foo-0.0.1/Cargo.toml.orig" ||
                 fname == b"foo-0.0.1/src/main.rs",
                 "unexpected filename: {:?}", file.header().path());
     }
@@ -138,3 +139,225 @@ all path dependencies must have a version specified when publishing.
 dependency `bar` does not specify a version
 "));
 });
+
+test!(dry_run {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "foo"
+            keywords = ["cli"]
+            categories = ["command-line-utilities"]
+
+            [badges]
+            travis-ci = { repository = "foo/foo" }
+        "#)
+        .file("src/main.rs", "fn main() {}");
+
+    assert_that(p.cargo_process("publish").arg("--dry-run"),
+                execs().with_status(0).with_stdout(&format!("\
+{updating} registry `{reg}`
+{packaging} foo v0.0.1 ({dir})
+{verifying} foo v0.0.1 ({dir})
+{compiling} foo v0.0.1 ({dir}[..])
+foo v0.0.1 would be uploaded with:
+    tarball: target[..]package[..]foo-0.0.1.crate ([..] bytes)
+    dependencies: 0
+    features: 0
+    authors: \n    description: foo
+    license: MIT
+    keywords: cli
+    categories: command-line-utilities
+    badges: travis-ci
+    readme: no
+",
+        updating = UPDATING,
+        packaging = PACKAGING,
+        verifying = VERIFYING,
+        compiling = COMPILING,
+        dir = p.url(),
+        reg = registry()))
+                .with_stderr("warning: aborting upload due to dry run\n"));
+
+    assert!(!upload_path().join("api/v1/crates/new").exists());
+});
+
+test!(dry_run_checks_limits {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "foo"
+            keywords = ["a", "b", "c", "d", "e", "f"]
+        "#)
+        .file("src/main.rs", "fn main() {}");
+
+    assert_that(p.cargo_process("publish").arg("--dry-run").arg("--no-verify"),
+                execs().with_status(101).with_stderr("\
+the package has 6 keywords, but at most 5 are allowed
+"));
+});
+
+test!(unreadable_readme {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "foo"
+            readme = "MISSING.md"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+
+    assert_that(p.cargo_process("publish").arg("--dry-run").arg("--no-verify"),
+                execs().with_status(101).with_stderr("\
+failed to read the readme file `MISSING.md`
+"));
+});
+
+test!(metadata_is_uploaded {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "foo"
+            links = "foo"
+            build = "build.rs"
+            categories = ["development-tools"]
+
+            [badges]
+            travis-ci = { repository = "foo/foo", branch = "master" }
+        "#)
+        .file("build.rs", "fn main() {}")
+        .file("src/main.rs", "fn main() {}");
+
+    assert_that(p.cargo_process("publish").arg("--no-verify"),
+                execs().with_status(0));
+
+    let mut f = File::open(&upload_path().join("api/v1/crates/new")).unwrap();
+    let mut sz = [0; 4];
+    assert_eq!(f.read(&mut sz).unwrap(), 4);
+    let sz = ((sz[0] as u32) <<  0) |
+             ((sz[1] as u32) <<  8) |
+             ((sz[2] as u32) << 16) |
+             ((sz[3] as u32) << 24);
+    let mut json = String::new();
+    f.take(sz as u64).read_to_string(&mut json).unwrap();
+    assert!(json.contains(r#""categories":["development-tools"]"#), "{}", json);
+    assert!(json.contains(r#""badges":{"travis-ci":{"branch":"master","repository":"foo/foo"}}"#),
+            "{}", json);
+    assert!(json.contains(r#""links":"foo""#), "{}", json);
+});
+
+test!(invalid_team_owner {
+    let p = project("foo");
+
+    assert_that(p.cargo_process("owner").arg("foo").arg("--add").arg("github:rust-lang"),
+                execs().with_status(101).with_stderr("\
+invalid team owner `github:rust-lang`, teams must be specified as `github:org:team`
+"));
+});
+
+test!(credential_provider {
+    if cfg!(windows) { return }
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "foo"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    p.build();
+
+    File::create(&paths::root().join(".cargo/config")).unwrap()
+        .write_all(format!(r#"
+            [registry]
+                index = "{reg}"
+                credential-provider = "true"
+        "#, reg = registry()).as_bytes()).unwrap();
+
+    assert_that(p.cargo("publish").arg("--no-verify"),
+                execs().with_status(101).with_stderr("\
+the credential provider `true` did not print an API token
+"));
+
+    // Nothing is uploaded by a dry run, so the provider isn't run at all.
+    assert_that(p.cargo("publish").arg("--dry-run").arg("--no-verify"),
+                execs().with_status(0));
+
+    File::create(&paths::root().join(".cargo/config")).unwrap()
+        .write_all(format!(r#"
+            [registry]
+                index = "{reg}"
+                credential-provider = "echo provided-token"
+        "#, reg = registry()).as_bytes()).unwrap();
+
+    assert_that(p.cargo("publish").arg("--no-verify"),
+                execs().with_status(0));
+    assert!(upload_path().join("api/v1/crates/new").exists());
+
+    // What the provider prints on stderr, such as a prompt, isn't captured.
+    File::create(&paths::root().join(".cargo/config")).unwrap()
+        .write_all(format!(r#"
+            [registry]
+                index = "{reg}"
+                credential-provider = ["sh", "-c", "echo 'Password:' >&2; echo t"]
+        "#, reg = registry()).as_bytes()).unwrap();
+
+    assert_that(p.cargo("publish").arg("--no-verify"),
+                execs().with_status(0).with_stderr_contains("Password:"));
+});
+
+test!(credential_provider_list {
+    if cfg!(windows) { return }
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "foo"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    p.build();
+
+    // The arguments of a list are passed as they are, spaces included.
+    File::create(&paths::root().join(".cargo/config")).unwrap()
+        .write_all(format!(r#"
+            [registry]
+                index = "{reg}"
+                credential-provider = ["sh", "-c", "test \"$1\" = 'a b' && echo t",
+                                       "sh", "a b"]
+        "#, reg = registry()).as_bytes()).unwrap();
+
+    assert_that(p.cargo("publish").arg("--no-verify"),
+                execs().with_status(0));
+    assert!(upload_path().join("api/v1/crates/new").exists());
+
+    File::create(&paths::root().join(".cargo/config")).unwrap()
+        .write_all(format!(r#"
+            [registry]
+                index = "{reg}"
+                credential-provider = []
+        "#, reg = registry()).as_bytes()).unwrap();
+
+    assert_that(p.cargo("publish").arg("--no-verify"),
+                execs().with_status(101).with_stderr("\
+`registry.credential-provider` in [..]config must name a program to run
+"));
+});
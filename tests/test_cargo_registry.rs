@@ -422,6 +422,51 @@ version required: *
 "));
 });
 
+test!(yanks_in_lockfiles_are_reported {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "*"
+            baz = "*"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    p.build();
+
+    r::mock_pkg("bar", "0.0.1", &[]);
+    r::mock_pkg("baz", "0.0.1", &[]);
+
+    assert_that(p.cargo("build"),
+                execs().with_status(0));
+
+    fs::remove_dir_all(&r::registry_path().join("3")).unwrap();
+
+    r::mock_pkg_yank("bar", "0.0.1", &[], true);
+    r::mock_pkg("baz", "0.0.1", &[]);
+
+    // pull in the new index without touching `bar`
+    assert_that(p.cargo("update").arg("-p").arg("baz"),
+                execs().with_status(0));
+
+    assert_that(p.cargo("build"),
+                execs().with_status(0).with_stderr("\
+warning: the following packages in Cargo.lock have been yanked from their registry:
+    bar v0.0.1 (registry file://[..])
+consider running `cargo update -p bar` to move off of them
+"));
+
+    assert_that(p.cargo("build").arg("--deny-yanked"),
+                execs().with_status(101).with_stderr("\
+the following packages in Cargo.lock have been yanked from their registry:
+    bar v0.0.1 (registry file://[..])
+consider running `cargo update -p bar` to move off of them
+"));
+});
+
 test!(update_with_lockfile_if_packages_missing {
     let p = project("foo")
         .file("Cargo.toml", r#"
@@ -527,6 +572,54 @@ test!(update_lockfile {
 ", updating = UPDATING, removing = REMOVING)));
 });
 
+test!(update_dry_run {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "*"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    p.build();
+
+    r::mock_pkg("bar", "0.0.1", &[]);
+    assert_that(p.cargo("build"),
+                execs().with_status(0));
+    let lockfile = p.root().join("Cargo.lock");
+    let mut before = String::new();
+    File::open(&lockfile).unwrap().read_to_string(&mut before).unwrap();
+
+    r::mock_pkg("spam", "0.2.5", &[]);
+    r::mock_pkg("bar", "0.0.2", &[("spam", "0.2.5", "")]);
+    assert_that(p.cargo("update").arg("--dry-run"),
+                execs().with_status(0).with_stdout(&format!("\
+{updating} registry `[..]`
+   Upgrading bar v0.0.1 (registry file://[..]) -> v0.0.2
+{adding} spam v0.2.5 (registry file://[..])
+", updating = UPDATING, adding = ADDING))
+                                       .with_stderr("\
+warning: not updating Cargo.lock due to dry run
+"));
+
+    assert_that(p.cargo("update").arg("--dry-run").arg("--json"),
+                execs().with_status(0).with_stdout("\
+[{\"name\":\"bar\",\"kind\":\"upgraded\",\"old_version\":\"0.0.1\",\
+\"new_version\":\"0.0.2\",\"old_source\":\"registry+file://[..]\",\
+\"new_source\":\"registry+file://[..]\"},\
+{\"name\":\"spam\",\"kind\":\"added\",\"old_version\":null,\
+\"new_version\":\"0.2.5\",\"old_source\":null,\
+\"new_source\":\"registry+file://[..]\"}]
+"));
+
+    let mut after = String::new();
+    File::open(&lockfile).unwrap().read_to_string(&mut after).unwrap();
+    assert_eq!(before, after);
+});
+
 test!(dev_dependency_not_used {
     let p = project("foo")
         .file("Cargo.toml", r#"
@@ -564,6 +657,47 @@ test!(login_with_no_cargo_dir {
                 execs().with_status(0));
 });
 
+test!(login_writes_credentials {
+    let home = paths::home().join("new-home");
+    let config = home.join(".cargo/config");
+    fs::create_dir_all(config.parent().unwrap()).unwrap();
+    File::create(&config).unwrap().write_all(br#"
+        [registry]
+        token = "old-token"
+
+        [build]
+        jobs = 1
+    "#).unwrap();
+
+    assert_that(process(&cargo_dir().join("cargo")).unwrap()
+                       .arg("login").arg("new-token")
+                       .cwd(&paths::root())
+                       .env("HOME", &home),
+                execs().with_status(0));
+
+    let credentials = home.join(".cargo/credentials");
+    let mut contents = String::new();
+    File::open(&credentials).unwrap().read_to_string(&mut contents).unwrap();
+    assert!(contents.contains(r#"token = "new-token""#), "{}", contents);
+    if cfg!(unix) {
+        assert_eq!(permissions(&credentials) & 0o777, 0o600);
+    }
+
+    // The token is no longer kept in the configuration
+    let mut contents = String::new();
+    File::open(&config).unwrap().read_to_string(&mut contents).unwrap();
+    assert!(!contents.contains("token"), "{}", contents);
+    assert!(contents.contains("jobs = 1"), "{}", contents);
+
+    #[cfg(unix)]
+    fn permissions(path: &::std::path::Path) -> u32 {
+        use std::os::unix::prelude::*;
+        fs::metadata(path).unwrap().permissions().mode()
+    }
+    #[cfg(not(unix))]
+    fn permissions(_path: &::std::path::Path) -> u32 { 0 }
+});
+
 test!(bad_license_file {
     let p = project("all")
         .file("Cargo.toml", r#"
@@ -164,6 +164,23 @@ test!(too_many_bins {
                                      to specify which one to run\n"));
 });
 
+test!(bin_name_is_not_a_pattern {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .file("src/bin/a.rs", "fn main() {}")
+        .file("src/bin/b.rs", "fn main() {}");
+
+    assert_that(p.cargo_process("run").arg("--bin").arg("a*"),
+                execs().with_status(101)
+                       .with_stderr("no bin target named `a*`\n"));
+});
+
 test!(specify_name {
     let p = project("foo")
         .file("Cargo.toml", r#"
@@ -510,3 +527,32 @@ hello
         sep = SEP
         )));
 });
+
+#[cfg(unix)]
+test!(target_runner {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/main.rs", r#"
+            fn main() { std::process::exit(2); }
+        "#)
+        .file(".cargo/config", &format!(r#"
+            [target.{}]
+            runner = ["sh", "runner.sh", "--runner-flag"]
+        "#, ::rustc_host()))
+        .file("runner.sh", r#"
+            echo "runner $@"
+            shift
+            exec "$@"
+        "#);
+
+    assert_that(p.cargo_process("run").arg("--").arg("an-arg"),
+                execs().with_status(2)
+                       .with_stdout_contains("\
+runner --runner-flag target[..]debug[..]foo an-arg
+"));
+});
@@ -1,4 +1,4 @@
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::prelude::*;
 use std::str;
 use std::thread;
@@ -2057,3 +2057,470 @@ test!(selective_test_wonky_profile {
 {running} `rustc src[..]lib.rs [..]`
 ", compiling = COMPILING, running = RUNNING)));
 });
+
+fn report_project() -> ::support::ProjectBuilder {
+    project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", r#"
+            /// ```
+            /// foo::foo();
+            /// ```
+            pub fn foo() {}
+
+            #[test] fn lib_ok() {}
+            #[test] #[ignore] fn lib_ignored() {}
+        "#)
+        .file("tests/a.rs", r#"
+            #[test] fn a_fails() { panic!() }
+        "#)
+}
+
+test!(report_json {
+    let p = report_project();
+
+    assert_that(p.cargo_process("test").arg("--no-fail-fast")
+                 .arg("--report").arg("report.json"),
+                execs().with_status(101)
+                       .with_stdout_contains("test lib_ok ... ok"));
+
+    let mut report = String::new();
+    File::open(&p.root().join("report.json")).unwrap()
+         .read_to_string(&mut report).unwrap();
+    for expected in [
+        r#""target":"foo","kind":"lib""#,
+        r#""exit_code":0,"success":true"#,
+        r#"{"name":"lib_ok","result":"ok"}"#,
+        r#"{"name":"lib_ignored","result":"ignored"}"#,
+        r#""target":"a","kind":"test""#,
+        r#""success":false,"tests":[{"name":"a_fails","result":"failed"}]"#,
+        r#""target":"foo","kind":"doc""#,
+    ].iter() {
+        assert!(report.contains(*expected), "`{}` not in {}", expected, report);
+    }
+});
+
+test!(report_non_utf8_output {
+    // More output than fits in a pipe follows the line which isn't UTF-8, so
+    // the test binary only finishes if all of its output is read.
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [[test]]
+            name = "noisy"
+            harness = false
+        "#)
+        .file("src/lib.rs", "")
+        .file("tests/noisy.rs", r#"
+            use std::io::prelude::*;
+
+            fn main() {
+                std::io::stdout().write_all(b"\xff\xfe\n").unwrap();
+                for i in 0..100000 {
+                    println!("line {}", i);
+                }
+                println!("test noisy ... ok");
+            }
+        "#);
+
+    assert_that(p.cargo_process("test").arg("--test").arg("noisy")
+                 .arg("--report").arg("report.json"),
+                execs().with_status(0)
+                       .with_stdout_contains("line 99999"));
+
+    let mut report = String::new();
+    File::open(&p.root().join("report.json")).unwrap()
+         .read_to_string(&mut report).unwrap();
+    assert!(report.contains(r#""success":true,"tests":[{"name":"noisy","result":"ok"}]"#),
+            "{}", report);
+});
+
+test!(report_junit {
+    let p = report_project();
+
+    assert_that(p.cargo_process("test").arg("--no-fail-fast")
+                 .arg("--report").arg("report.xml")
+                 .arg("--report-format").arg("junit"),
+                execs().with_status(101));
+
+    let mut report = String::new();
+    File::open(&p.root().join("report.xml")).unwrap()
+         .read_to_string(&mut report).unwrap();
+    assert!(report.starts_with("<?xml"), "{}", report);
+    for expected in [
+        r#"<testsuite name="lib foo" package="foo v0.0.1 ("#,
+        r#"tests="2" failures="0" errors="0" skipped="1""#,
+        r#"<testcase classname="foo" name="lib_ok"/>"#,
+        r#"<testsuite name="test a" package="foo v0.0.1 ("#,
+        "<testcase classname=\"a\" name=\"a_fails\">\n      <failure/>",
+    ].iter() {
+        assert!(report.contains(*expected), "`{}` not in {}", expected, report);
+    }
+});
+
+test!(parallel_runs_binaries_at_the_same_time {
+    // Each test waits for the other one to start, so they only pass when both
+    // binaries run at the same time.
+    let waits_for = |me: &str, other: &str| format!(r#"
+        use std::fs::{{self, File}};
+        use std::thread;
+
+        #[test]
+        fn {me}() {{
+            File::create("{me}_started").unwrap();
+            for _ in 0..100 {{
+                if fs::metadata("{other}_started").is_ok() {{
+                    return
+                }}
+                thread::sleep_ms(100);
+            }}
+            panic!("{other} never started");
+        }}
+    "#, me = me, other = other);
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .file("tests/a.rs", &waits_for("a", "b"))
+        .file("tests/b.rs", &waits_for("b", "a"));
+
+    assert_that(p.cargo_process("test").arg("--parallel").arg("-j").arg("2"),
+                execs().with_status(0)
+                       .with_stdout_contains("test a ... ok")
+                       .with_stdout_contains("test b ... ok"));
+});
+
+test!(parallel_failure {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .file("tests/a.rs", "#[test] fn a_fails() { panic!() }")
+        .file("tests/b.rs", "#[test] fn b_passes() {}");
+
+    assert_that(p.cargo_process("test").arg("--parallel").arg("-j").arg("2")
+                 .arg("--no-fail-fast"),
+                execs().with_status(101)
+                       .with_stdout_contains("test a_fails ... FAILED")
+                       .with_stdout_contains("test b_passes ... ok"));
+});
+
+#[cfg(unix)]
+test!(target_runner {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "#[test] fn lib_test() {}")
+        .file(".cargo/config", &format!(r#"
+            [target.{}]
+            runner = "sh runner.sh"
+        "#, ::rustc_host()))
+        .file("runner.sh", r#"
+            echo "runner $1"
+            exec "$@"
+        "#);
+
+    assert_that(p.cargo_process("test"),
+                execs().with_status(0)
+                       .with_stdout_contains("runner [..]target[..]debug[..]foo-[..]")
+                       .with_stdout_contains("test lib_test ... ok"));
+});
+
+test!(doc_only {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dev-dependencies]
+            b = { path = "b" }
+        "#)
+        .file("src/lib.rs", r#"
+            /// ```
+            /// extern crate b;
+            /// ```
+            pub fn foo() {}
+
+            #[test]
+            fn unit_test() { panic!() }
+        "#)
+        .file("tests/integration.rs", "#[test] fn integration() { panic!() }")
+        .file("b/Cargo.toml", r#"
+            [package]
+            name = "b"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("b/src/lib.rs", "");
+
+    assert_that(p.cargo_process("test").arg("--doc"),
+                execs().with_status(0).with_stdout(format!("\
+{compiling} b v0.0.1 ([..])
+{compiling} foo v0.0.1 ([..])
+{doctest} foo
+
+running 1 test
+test foo_0 ... ok
+
+test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured
+
+", compiling = COMPILING, doctest = DOCTEST)));
+
+    // Neither the unit tests nor the integration tests were compiled.
+    let tests = fs::read_dir(p.root().join("target/debug")).unwrap().map(|e| {
+        e.unwrap().file_name().into_string().unwrap()
+    }).filter(|name| {
+        name.starts_with("foo-") || name.starts_with("integration-")
+    }).collect::<Vec<_>>();
+    assert!(tests.is_empty(), "{:?}", tests);
+});
+
+test!(doctest_bins_examples_and_tests {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [[bin]]
+            name = "tested"
+            doctest = true
+
+            [[bin]]
+            name = "untested"
+
+            [[example]]
+            name = "ex"
+            doctest = true
+
+            [[test]]
+            name = "integration"
+            doctest = true
+        "#)
+        .file("src/lib.rs", "pub fn lib() {}")
+        .file("src/bin/tested.rs", r#"
+            /// ```
+            /// extern crate foo;
+            /// foo::lib();
+            /// ```
+            fn main() {}
+        "#)
+        .file("src/bin/untested.rs", r#"
+            /// ```
+            /// panic!();
+            /// ```
+            fn main() {}
+        "#)
+        .file("examples/ex.rs", r#"
+            /// ```
+            /// extern crate foo;
+            /// foo::lib();
+            /// ```
+            fn main() {}
+        "#)
+        .file("tests/integration.rs", r#"
+            /// ```
+            /// extern crate foo;
+            /// foo::lib();
+            /// ```
+            pub fn helper() {}
+
+            #[test]
+            fn integration() { panic!() }
+        "#);
+
+    assert_that(p.cargo_process("test").arg("--doc"),
+                execs().with_status(0)
+                       .with_stdout_contains(format!("{} foo", DOCTEST))
+                       .with_stdout_contains(format!("{} tested", DOCTEST))
+                       .with_stdout_contains(format!("{} ex", DOCTEST))
+                       .with_stdout_contains(format!("{} integration", DOCTEST))
+                       .with_stdout_contains("test main_0 ... ok")
+                       .with_stdout_contains("test helper_0 ... ok"));
+    assert_that(p.cargo("test").arg("--doc").arg("-v"),
+                execs().with_status(0)
+                       .with_stdout_contains("\
+[..]rustdoc --test [..]bin[..]tested.rs [..]--extern foo=[..]libfoo.rlib[..]"));
+});
+
+test!(doc_with_target_filter {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "");
+
+    assert_that(p.cargo_process("test").arg("--doc").arg("--lib"),
+                execs().with_status(101).with_stderr("\
+Can't mix --doc with other target selecting options
+"));
+});
+
+test!(doctest_cfgs_of_selected_package {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar" }
+        "#)
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+            build = "build.rs"
+        "#)
+        .file("bar/build.rs", r#"
+            fn main() { println!("cargo:rustc-cfg=from_build_script"); }
+        "#)
+        .file("bar/src/lib.rs", r#"
+            /// ```
+            /// bar::bar();
+            /// ```
+            #[cfg(from_build_script)]
+            pub fn bar() {}
+        "#);
+
+    assert_that(p.cargo_process("test").arg("--doc").arg("-p").arg("bar"),
+                execs().with_status(0)
+                       .with_stdout_contains(format!("{} bar", DOCTEST))
+                       .with_stdout_contains("test bar_0 ... ok"));
+});
+
+fn filtered_project() -> ::support::ProjectBuilder {
+    project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "#[test] fn lib_test() {}")
+        .file("tests/net_tcp.rs", "#[test] fn tcp() {}")
+        .file("tests/net_udp.rs", "#[test] fn udp() {}")
+        .file("tests/disk.rs", "#[test] fn disk() {}")
+}
+
+test!(test_filter_glob {
+    let p = filtered_project();
+
+    assert_that(p.cargo_process("test").arg("--test").arg("net_*"),
+                execs().with_status(0).with_stdout(format!("\
+{compiling} foo v0.0.1 ([..])
+{running} target[..]debug[..]net_tcp-[..]
+
+running 1 test
+test tcp ... ok
+
+test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured
+
+{running} target[..]debug[..]net_udp-[..]
+
+running 1 test
+test udp ... ok
+
+test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured
+
+", compiling = COMPILING, running = RUNNING)));
+});
+
+test!(test_filter_all_tests {
+    let p = filtered_project();
+
+    assert_that(p.cargo_process("test").arg("--tests"),
+                execs().with_status(0)
+                       .with_stdout_contains("test disk ... ok")
+                       .with_stdout_contains("test tcp ... ok")
+                       .with_stdout_contains("test udp ... ok"));
+});
+
+test!(test_filter_glob_matches_nothing {
+    let p = filtered_project();
+
+    assert_that(p.cargo_process("test").arg("--test").arg("fs_*"),
+                execs().with_status(101).with_stderr("\
+no test target matches the pattern `fs_*`
+"));
+});
+
+test!(test_filter_package_glob {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            net_tcp = { path = "net_tcp" }
+            net_udp = { path = "net_udp" }
+            disk = { path = "disk" }
+        "#)
+        .file("src/lib.rs", "#[test] fn foo() {}")
+        .file("net_tcp/Cargo.toml", r#"
+            [package]
+            name = "net_tcp"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("net_tcp/src/lib.rs", "#[test] fn tcp() {}")
+        .file("net_udp/Cargo.toml", r#"
+            [package]
+            name = "net_udp"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("net_udp/src/lib.rs", "#[test] fn udp() {}")
+        .file("disk/Cargo.toml", r#"
+            [package]
+            name = "disk"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("disk/src/lib.rs", "#[test] fn disk() {}");
+
+    p.build();
+    let output = p.cargo("test").arg("-p").arg("net_*").exec_with_output()
+                  .unwrap();
+    let stdout = str::from_utf8(&output.stdout).unwrap();
+    assert!(stdout.contains("test tcp ... ok"), "{}", stdout);
+    assert!(stdout.contains("test udp ... ok"), "{}", stdout);
+    assert!(!stdout.contains("test disk ... ok"), "{}", stdout);
+    assert!(!stdout.contains("test foo ... ok"), "{}", stdout);
+
+    assert_that(p.cargo("test").arg("-p").arg("fs_*"),
+                execs().with_status(101).with_stderr("\
+could not find package matching spec `fs_*`
+"));
+});
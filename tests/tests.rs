@@ -36,20 +36,24 @@ mod test_cargo;
 mod test_cargo_bench;
 mod test_cargo_build_auth;
 mod test_cargo_build_lib;
+mod test_cargo_cache;
 mod test_cargo_clean;
 mod test_cargo_compile;
 mod test_cargo_compile_custom_build;
 mod test_cargo_compile_git_deps;
 mod test_cargo_compile_path_deps;
 mod test_cargo_compile_plugins;
+mod test_cargo_concurrent;
 mod test_cargo_cross_compile;
 mod test_cargo_doc;
 mod test_cargo_features;
 mod test_cargo_fetch;
 mod test_cargo_freshness;
 mod test_cargo_generate_lockfile;
+mod test_cargo_init;
 mod test_cargo_install;
 mod test_cargo_new;
+mod test_cargo_outdated;
 mod test_cargo_package;
 mod test_cargo_profiles;
 mod test_cargo_publish;